@@ -0,0 +1,11 @@
+ALTER TABLE warehouse_package_version
+    DROP COLUMN md5sum;
+
+ALTER TABLE warehouse_package_version
+    DROP COLUMN sha256sum;
+
+ALTER TABLE warehouse_package
+    DROP COLUMN md5sum;
+
+ALTER TABLE warehouse_package
+    DROP COLUMN sha256sum;
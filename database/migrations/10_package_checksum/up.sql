@@ -0,0 +1,11 @@
+ALTER TABLE warehouse_package
+    ADD COLUMN sha256sum VARCHAR(64) NOT NULL DEFAULT '';
+
+ALTER TABLE warehouse_package
+    ADD COLUMN md5sum VARCHAR(32) NOT NULL DEFAULT '';
+
+ALTER TABLE warehouse_package_version
+    ADD COLUMN sha256sum VARCHAR(64) NOT NULL DEFAULT '';
+
+ALTER TABLE warehouse_package_version
+    ADD COLUMN md5sum VARCHAR(32) NOT NULL DEFAULT '';
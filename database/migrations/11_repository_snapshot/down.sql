@@ -0,0 +1,5 @@
+ALTER TABLE warehouse_repository
+    DROP COLUMN snapshot_retention;
+
+ALTER TABLE warehouse_repository
+    DROP COLUMN snapshot_interval;
@@ -0,0 +1,5 @@
+ALTER TABLE warehouse_repository
+    ADD COLUMN snapshot_interval INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE warehouse_repository
+    ADD COLUMN snapshot_retention INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+DROP TABLE warehouse_package_download;
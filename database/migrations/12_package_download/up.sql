@@ -0,0 +1,13 @@
+CREATE TABLE warehouse_package_download
+(
+    id           VARCHAR(36)  NOT NULL,
+    day          DATE         NOT NULL,
+    version      VARCHAR(255) NOT NULL,
+    architecture VARCHAR(255) NOT NULL,
+    count        BIGINT       NOT NULL,
+    package_id   VARCHAR(36)  NOT NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (package_id) REFERENCES warehouse_package (id)
+);
+
+CREATE UNIQUE INDEX warehouse_package_download_idx1 ON warehouse_package_download (package_id, version, architecture, day);
@@ -0,0 +1,2 @@
+ALTER TABLE warehouse_repository
+    ADD COLUMN require_signature BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+ALTER TABLE warehouse_repository
+    ADD COLUMN description VARCHAR(255) NOT NULL DEFAULT '';
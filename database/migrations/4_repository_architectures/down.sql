@@ -0,0 +1,2 @@
+ALTER TABLE warehouse_repository
+    DROP COLUMN architectures;
@@ -0,0 +1,42 @@
+ALTER TABLE warehouse_repository
+    ADD COLUMN architectures VARCHAR(255) NOT NULL DEFAULT 'x86_64';
+
+-- Existing repositories serve the architectures of their packages, and keep x86_64 only when they
+-- have none. The list is joined recursively, as string aggregation differs between databases.
+UPDATE warehouse_repository
+SET architectures = (
+    WITH RECURSIVE
+        ranked_architecture (repository_id, architecture, rank_number) AS (
+            SELECT DISTINCT listed.repository_id,
+                            listed.architecture,
+                            (SELECT COUNT(DISTINCT other.architecture)
+                             FROM warehouse_package other
+                             WHERE other.repository_id = listed.repository_id
+                               AND other.architecture <> 'any'
+                               AND other.architecture <= listed.architecture)
+            FROM warehouse_package listed
+            WHERE listed.architecture <> 'any'
+        ),
+        joined_architecture (repository_id, rank_number, architectures) AS (
+            SELECT repository_id, rank_number, '' || architecture
+            FROM ranked_architecture
+            WHERE rank_number = 1
+            UNION ALL
+            SELECT joined.repository_id,
+                   ranked.rank_number,
+                   joined.architectures || ' ' || ranked.architecture
+            FROM joined_architecture joined
+                     INNER JOIN ranked_architecture ranked
+                                ON ranked.repository_id = joined.repository_id
+                                    AND ranked.rank_number = joined.rank_number + 1
+        )
+    SELECT architectures
+    FROM joined_architecture
+    WHERE repository_id = warehouse_repository.id
+    ORDER BY rank_number DESC
+    LIMIT 1
+)
+WHERE EXISTS(SELECT 1
+             FROM warehouse_package
+             WHERE repository_id = warehouse_repository.id
+               AND architecture <> 'any');
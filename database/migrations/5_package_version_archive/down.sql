@@ -0,0 +1,5 @@
+ALTER TABLE warehouse_package_version
+    DROP COLUMN extension;
+
+ALTER TABLE warehouse_repository
+    DROP COLUMN retention;
@@ -0,0 +1,5 @@
+ALTER TABLE warehouse_repository
+    ADD COLUMN retention INTEGER NOT NULL DEFAULT 3;
+
+ALTER TABLE warehouse_package_version
+    ADD COLUMN extension VARCHAR(255) NOT NULL DEFAULT '';
@@ -0,0 +1,11 @@
+ALTER TABLE warehouse_package_dependency
+    DROP COLUMN description;
+
+ALTER TABLE warehouse_package_dependency
+    DROP COLUMN version;
+
+ALTER TABLE warehouse_package_dependency
+    DROP COLUMN operator;
+
+ALTER TABLE warehouse_package_dependency
+    DROP COLUMN kind;
@@ -0,0 +1,11 @@
+ALTER TABLE warehouse_package_dependency
+    ADD COLUMN kind VARCHAR(255) NOT NULL DEFAULT 'depend';
+
+ALTER TABLE warehouse_package_dependency
+    ADD COLUMN operator VARCHAR(255) NOT NULL DEFAULT '';
+
+ALTER TABLE warehouse_package_dependency
+    ADD COLUMN version VARCHAR(255) NOT NULL DEFAULT '';
+
+ALTER TABLE warehouse_package_dependency
+    ADD COLUMN description VARCHAR(255) NOT NULL DEFAULT '';
@@ -0,0 +1,5 @@
+ALTER TABLE warehouse_repository
+    DROP COLUMN strict_dependencies;
+
+ALTER TABLE warehouse_repository
+    DROP COLUMN depends_on;
@@ -0,0 +1,5 @@
+ALTER TABLE warehouse_repository
+    ADD COLUMN depends_on VARCHAR(255) NOT NULL DEFAULT '';
+
+ALTER TABLE warehouse_repository
+    ADD COLUMN strict_dependencies BOOLEAN NOT NULL DEFAULT FALSE;
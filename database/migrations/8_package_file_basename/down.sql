@@ -0,0 +1,2 @@
+ALTER TABLE warehouse_package_file
+    DROP COLUMN basename;
@@ -0,0 +1,4 @@
+ALTER TABLE warehouse_package_file
+    ADD COLUMN basename VARCHAR(255) NOT NULL DEFAULT '';
+
+CREATE INDEX warehouse_package_file_basename_idx ON warehouse_package_file (basename);
@@ -0,0 +1 @@
+DROP TABLE warehouse_user_token;
@@ -0,0 +1,10 @@
+CREATE TABLE warehouse_user_token
+(
+    id            VARCHAR(36)        NOT NULL,
+    creation_date TIMESTAMP          NOT NULL,
+    name          VARCHAR(255)       NOT NULL,
+    hash          VARCHAR(64) UNIQUE NOT NULL,
+    user_id       VARCHAR(36)        NOT NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (user_id) REFERENCES warehouse_user (id)
+);
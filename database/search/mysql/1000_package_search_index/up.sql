@@ -0,0 +1 @@
+CREATE FULLTEXT INDEX warehouse_package_search_idx ON warehouse_package (name, description);
@@ -0,0 +1 @@
+DROP INDEX warehouse_package_search_idx;
@@ -0,0 +1,2 @@
+CREATE INDEX warehouse_package_search_idx ON warehouse_package
+    USING GIN (to_tsvector('simple', name || ' ' || description));
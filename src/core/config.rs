@@ -7,15 +7,21 @@ use std::{
 };
 
 macro_rules! config {
+    (@debug #[secret] $value:expr) => {
+        &"<redacted>"
+    };
+    (@debug $value:expr) => {
+        &$value
+    };
     {
         $($config:ident: $config_type:ident {
-            $($field:ident: $field_type:ty => $field_default:expr,)*
+            $($(#[$attribute:ident])? $field:ident: $field_type:ty => $field_default:expr,)*
         },)*
     } => {
         use {
             crate::core::error::LoadConfigError,
             serde::{Deserialize, Serialize},
-            std::{fs, str::FromStr},
+            std::{fmt, fs, str::FromStr},
         };
 
         #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -52,7 +58,7 @@ macro_rules! config {
         }
 
         $(
-            #[derive(Clone, Debug, Deserialize, Serialize)]
+            #[derive(Clone, Deserialize, Serialize)]
             #[serde(default)]
             pub struct $config_type {
                 $(
@@ -60,6 +66,20 @@ macro_rules! config {
                 )*
             }
 
+            impl fmt::Debug for $config_type {
+                fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter
+                        .debug_struct(stringify!($config_type))
+                        $(
+                            .field(
+                                stringify!($field),
+                                config!(@debug $(#[$attribute])? self.$field),
+                            )
+                        )*
+                        .finish()
+                }
+            }
+
             impl Default for $config_type {
                 fn default() -> $config_type {
                     $config_type {
@@ -113,6 +133,12 @@ config! {
         level: LevelFilter => LevelFilter::Info,
         time_format: String => "%Y-%m-%d %H:%M:%S%.3f",
     },
+    resolver: ResolverConfig {
+        sync_dir: String => format!("{directory}/{filename}",
+            directory = path::config_dir().display(),
+            filename = "sync"
+        ),
+    },
     server: ServerConfig {
         ip_address: IpAddr => Ipv6Addr::UNSPECIFIED,
         port: u16 => 8080u16,
@@ -122,11 +148,40 @@ config! {
     session: SessionConfig {
         cookie_name: String => concat!(env!("CARGO_PKG_NAME"), "_auth"),
         cookie_secure: bool => false,
+        #[secret]
         secret_key: String => base64::encode(&rand::thread_rng().gen::<[u8; 32]>()),
     },
+    signing: SigningConfig {
+        enabled: bool => false,
+        #[secret]
+        passphrase: String => "",
+        private_key: String => format!("{directory}/{filename}",
+            directory = path::config_dir().display(),
+            filename = "signing_key.asc"
+        ),
+    },
+    storage: StorageConfig {
+        access_key: String => "",
+        backend: String => "local",
+        bucket: String => "",
+        endpoint: String => "",
+        path_style: bool => true,
+        prefix: String => "",
+        presign_downloads: bool => true,
+        presign_expiry: u64 => 300u64,
+        region: String => "us-east-1",
+        #[secret]
+        secret_key: String => "",
+    },
     ui: UIConfig {
         paging_num: u32 => 10u32,
         primary_color: String => "#484a90",
         primary_dark_color: String => "#2f3177",
     },
+    verification: VerificationConfig {
+        keyring: String => format!("{directory}/{filename}",
+            directory = path::config_dir().display(),
+            filename = "keyring.asc"
+        ),
+    },
 }
@@ -1,15 +1,69 @@
 use crate::view;
 use actix_web::{
-    dev::ServiceResponse, http::StatusCode, middleware::errhandlers::ErrorHandlerResponse,
-    HttpResponse, ResponseError,
+    dev::ServiceResponse, error::BlockingError, http::StatusCode,
+    middleware::errhandlers::ErrorHandlerResponse, HttpResponse, ResponseError,
 };
 use derive_more::{Display, From};
 use log::error;
+use serde::Serialize;
+use std::fmt::Debug;
 
 pub type WarehouseResult<T = ()> = Result<T, WarehouseError>;
 
+#[derive(Debug, Display, From)]
+pub enum CheckRepositoryError {
+    #[display(fmt = "{}", _0)]
+    DieselError(diesel::result::Error),
+
+    #[display(fmt = "{}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "{}", _0)]
+    ReadPackageError(ReadPackageError),
+
+    #[display(fmt = "{}", _0)]
+    WriteRepositoryError(WriteRepositoryError),
+}
+
+#[derive(Debug, Display, From)]
+pub enum CreateSnapshotError {
+    #[display(fmt = "{}", _0)]
+    DieselError(diesel::result::Error),
+
+    #[display(fmt = "{}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "{}", _0)]
+    ReadPackageError(ReadPackageError),
+
+    #[display(fmt = "Snapshot {} already exists", _0)]
+    #[from(ignore)]
+    SnapshotExists(String),
+}
+
+#[derive(Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
 #[derive(Debug, Display, From)]
 pub enum ImportPackageError {
+    #[display(fmt = "Package already exists for architecture {}", _0)]
+    #[from(ignore)]
+    ArchitectureConflict(String),
+
+    #[display(fmt = "Archive not found")]
+    #[from(ignore)]
+    ArchiveNotFound,
+
+    #[display(fmt = "Checksum mismatch, expected {} but got {}", expected, actual)]
+    #[from(ignore)]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[display(fmt = "Repository database not found in {}", _0)]
+    #[from(ignore)]
+    DatabaseNotFound(String),
+
     #[display(fmt = "{}", _0)]
     DieselError(diesel::result::Error),
 
@@ -38,6 +92,13 @@ pub enum ImportPackageError {
     #[from(ignore)]
     RepositoryNotFound(String),
 
+    #[display(fmt = "{}", _0)]
+    ResolveDependenciesError(ResolveDependenciesError),
+
+    #[display(fmt = "Signature required")]
+    #[from(ignore)]
+    SignatureRequired,
+
     #[display(fmt = "{}", _0)]
     TempFilePersistError(tempfile::PersistError),
 
@@ -48,6 +109,24 @@ pub enum ImportPackageError {
     #[display(fmt = "Unauthorized update")]
     #[from(ignore)]
     UnauthorizedUpdate,
+
+    #[display(fmt = "Archive not listed in the repository database")]
+    #[from(ignore)]
+    UnlistedArchive,
+
+    #[display(fmt = "Unmet dependencies {}", "_0.join(\", \")")]
+    #[from(ignore)]
+    UnmetDependencies(Vec<String>),
+
+    #[display(fmt = "Unsupported architecture {}", _0)]
+    #[from(ignore)]
+    UnsupportedArchitecture(String),
+
+    #[display(fmt = "{}", _0)]
+    VerifySignatureError(VerifySignatureError),
+
+    #[display(fmt = "{}", _0)]
+    WriteRepositoryError(WriteRepositoryError),
 }
 
 #[derive(Debug, Display, From)]
@@ -75,9 +154,39 @@ pub enum LoadConfigError {
 }
 
 #[derive(Debug, Display, From)]
-pub enum ReadPackageError {
+pub enum OpenStorageError {
+    #[display(fmt = "Invalid storage endpoint: {}", _0)]
+    InvalidEndpoint(url::ParseError),
+
+    #[display(fmt = "Unknown storage backend {}", _0)]
+    #[from(ignore)]
+    UnknownBackend(String),
+}
+
+#[derive(Debug, Display, From)]
+pub enum PromotePackageError {
     #[display(fmt = "{}", _0)]
-    AlpmError(alpm::Error),
+    DieselError(diesel::result::Error),
+
+    #[display(fmt = "{}", _0)]
+    ImportPackageError(ImportPackageError),
+
+    #[display(fmt = "{}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "Package already in the target repository")]
+    #[from(ignore)]
+    SameRepository,
+
+    #[display(fmt = "{}", _0)]
+    WriteRepositoryError(WriteRepositoryError),
+}
+
+#[derive(Debug, Display, From)]
+pub enum ReadPackageError {
+    #[display(fmt = "Invalid package: {}", _0)]
+    #[from(ignore)]
+    InvalidPackage(String),
 
     #[display(fmt = "{}", _0)]
     Io(std::io::Error),
@@ -87,11 +196,45 @@ pub enum ReadPackageError {
     UnsupportedFileType,
 }
 
+#[derive(Debug, Display, From)]
+pub enum ResolveDependenciesError {
+    #[display(fmt = "{}", _0)]
+    DieselError(diesel::result::Error),
+
+    #[display(fmt = "{}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "{}", _0)]
+    ReadPackageError(ReadPackageError),
+}
+
+#[derive(Debug, Display, From)]
+pub enum RollbackPackageError {
+    #[display(fmt = "{}", _0)]
+    DieselError(diesel::result::Error),
+
+    #[display(fmt = "{}", _0)]
+    ImportPackageError(ImportPackageError),
+
+    #[display(fmt = "{}", _0)]
+    ReadPackageError(ReadPackageError),
+
+    #[display(fmt = "Version not found")]
+    #[from(ignore)]
+    VersionNotFound,
+
+    #[display(fmt = "{}", _0)]
+    WriteRepositoryError(WriteRepositoryError),
+}
+
 #[derive(Debug, Display, From)]
 pub enum RunPendingMigrationsError {
     #[display(fmt = "{}", _0)]
     DieselConnectionError(diesel::ConnectionError),
 
+    #[display(fmt = "{}", _0)]
+    DieselError(diesel::result::Error),
+
     #[display(fmt = "{}", _0)]
     DieselMigrationsError(diesel_migrations::RunMigrationsError),
 
@@ -111,6 +254,28 @@ pub enum SetupLoggerError {
     SetLoggerError(log::SetLoggerError),
 }
 
+#[derive(Debug, Display, From)]
+pub enum SignFileError {
+    #[display(fmt = "{}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "{}", _0)]
+    PgpError(pgp::errors::Error),
+}
+
+#[derive(Debug, Display, From)]
+pub enum VerifySignatureError {
+    #[display(fmt = "{}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "{}", _0)]
+    PgpError(pgp::errors::Error),
+
+    #[display(fmt = "Unknown signing key")]
+    #[from(ignore)]
+    UnknownKey,
+}
+
 #[derive(Debug, Display, From)]
 pub enum WarehouseError {
     #[display(fmt = "{} not found in app data", _0)]
@@ -123,6 +288,16 @@ pub enum WarehouseError {
     #[display(fmt = "{}", _0)]
     BcryptError(bcrypt::BcryptError),
 
+    #[display(fmt = "Blocking operation canceled")]
+    #[from(ignore)]
+    BlockingCanceled,
+
+    #[display(fmt = "{}", _0)]
+    CheckRepositoryError(CheckRepositoryError),
+
+    #[display(fmt = "{}", _0)]
+    CreateSnapshotError(CreateSnapshotError),
+
     #[display(fmt = "{}", _0)]
     DieselError(diesel::result::Error),
 
@@ -143,20 +318,70 @@ pub enum WarehouseError {
     #[display(fmt = "{}", _0)]
     LoadConfigError(LoadConfigError),
 
+    #[display(fmt = "{}", _0)]
+    OpenStorageError(OpenStorageError),
+
+    #[display(fmt = "{}", _0)]
+    PromotePackageError(PromotePackageError),
+
+    #[display(fmt = "{}", _0)]
+    QuickXmlError(quick_xml::Error),
+
     #[display(fmt = "{}", _0)]
     R2d2Error(r2d2::Error),
 
     #[display(fmt = "{}", _0)]
     RegexError(regex::Error),
 
+    #[display(fmt = "{}", _0)]
+    RollbackPackageError(RollbackPackageError),
+
     #[display(fmt = "{}", _0)]
     RunPendingMigrationsError(RunPendingMigrationsError),
 
     #[display(fmt = "{}", _0)]
     SetupLoggerError(SetupLoggerError),
 
+    #[display(fmt = "{}", _0)]
+    SignFileError(SignFileError),
+
     #[display(fmt = "{}", _0)]
     TeraError(tera::Error),
+
+    #[display(fmt = "Unknown command {}", _0)]
+    #[from(ignore)]
+    UnknownCommand(String),
+
+    #[display(fmt = "{}", _0)]
+    WriteRepositoryError(WriteRepositoryError),
+}
+
+#[derive(Debug, Display, From)]
+pub enum WriteRepositoryError {
+    #[display(fmt = "{}", _0)]
+    DieselError(diesel::result::Error),
+
+    #[display(fmt = "{}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "{}", _0)]
+    SignFileError(SignFileError),
+
+    #[display(fmt = "{}", _0)]
+    TempFilePersistError(tempfile::PersistError),
+
+    #[display(fmt = "Unsupported repository extension {}", _0)]
+    #[from(ignore)]
+    UnsupportedExtension(String),
+}
+
+impl<E: Debug + Into<WarehouseError>> From<BlockingError<E>> for WarehouseError {
+    fn from(error: BlockingError<E>) -> WarehouseError {
+        match error {
+            BlockingError::Error(error) => error.into(),
+            BlockingError::Canceled => WarehouseError::BlockingCanceled,
+        }
+    }
 }
 
 impl ResponseError for WarehouseError {
@@ -167,6 +392,9 @@ impl ResponseError for WarehouseError {
             | WarehouseError::ImportPackageError(ImportPackageError::TextFieldNotFound(..))
             | WarehouseError::InvalidFormData
             | WarehouseError::InvalidPathData => StatusCode::BAD_REQUEST,
+            WarehouseError::RollbackPackageError(RollbackPackageError::VersionNotFound) => {
+                StatusCode::NOT_FOUND
+            }
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         })
     }
@@ -178,6 +406,22 @@ pub fn handle<B>(service: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerR
             error!("Error in response: {:?}", error);
         }
     }
+    // API clients get JSON, and responses built by the API handlers are left untouched.
+    if service.request().path().starts_with("/api/") {
+        if service.response().error().is_none() {
+            return Ok(ErrorHandlerResponse::Response(service));
+        }
+        let response = HttpResponse::build(service.status()).json(ErrorResponse {
+            error: service
+                .status()
+                .canonical_reason()
+                .unwrap_or_default()
+                .to_string(),
+        });
+        return Ok(ErrorHandlerResponse::Response(
+            service.into_response(response.into_body()),
+        ));
+    }
     let response = (view!(service.request(), "misc/error", ["status" => &service.status().as_u16()])
         as WarehouseResult<HttpResponse>)?;
     Ok(ErrorHandlerResponse::Response(
@@ -1,10 +1,22 @@
-use crate::core::error::{RunPendingMigrationsError, WarehouseError};
+use crate::{
+    core::error::{RunPendingMigrationsError, WarehouseError},
+    service::package::model::{Dependency, File},
+};
 use actix_web::{dev::Payload, web::Data, FromRequest, HttpRequest};
+use diesel_migrations::MigrationConnection;
 use futures::future::{self, Ready};
 use log::info;
 use std::{fs, ops::Deref, path::Path};
 
 pub mod schema;
+pub mod search;
+
+#[cfg(feature = "mysql")]
+pub type Backend = diesel::mysql::Mysql;
+#[cfg(feature = "postgres")]
+pub type Backend = diesel::pg::Pg;
+#[cfg(feature = "sqlite")]
+pub type Backend = diesel::sqlite::Sqlite;
 
 #[cfg(feature = "mysql")]
 pub type Connection = diesel::MysqlConnection;
@@ -13,12 +25,29 @@ pub type Connection = diesel::PgConnection;
 #[cfg(feature = "sqlite")]
 pub type Connection = diesel::SqliteConnection;
 
+/// Type to cast sums to, as they are wider than a 64-bit integer on MySQL and PostgreSQL.
+#[cfg(feature = "mysql")]
+pub const BIGINT_CAST: &str = "SIGNED";
+#[cfg(feature = "postgres")]
+pub const BIGINT_CAST: &str = "BIGINT";
+#[cfg(feature = "sqlite")]
+pub const BIGINT_CAST: &str = "BIGINT";
+
+/// Version of the migration giving repositories their architectures.
+const ARCHITECTURES_MIGRATION: &str = "4";
+
 pub type Pool = diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<Connection>>;
 
 pub struct PooledConnection {
     inner: r2d2::PooledConnection<diesel::r2d2::ConnectionManager<Connection>>,
 }
 
+impl PooledConnection {
+    pub fn get(pool: &Pool) -> Result<PooledConnection, r2d2::Error> {
+        Ok(PooledConnection { inner: pool.get()? })
+    }
+}
+
 impl Deref for PooledConnection {
     type Target = r2d2::PooledConnection<diesel::r2d2::ConnectionManager<Connection>>;
 
@@ -34,8 +63,8 @@ impl FromRequest for PooledConnection {
 
     fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
         if let Some(pool) = request.app_data::<Data<Pool>>() {
-            match pool.get() {
-                Ok(connection) => future::ok(PooledConnection { inner: connection }),
+            match PooledConnection::get(pool) {
+                Ok(connection) => future::ok(connection),
                 Err(error) => future::err(error.into()),
             }
         } else {
@@ -46,18 +75,52 @@ impl FromRequest for PooledConnection {
 
 embed_migrations!("database/migrations");
 
-pub fn run_pending_migrations(database_url: &str) -> Result<(), RunPendingMigrationsError> {
+// Full-text indexes need backend-specific DDL, so they live outside the shared migrations.
+#[cfg(feature = "mysql")]
+mod search_migrations {
+    embed_migrations!("database/search/mysql");
+}
+#[cfg(feature = "postgres")]
+mod search_migrations {
+    embed_migrations!("database/search/postgres");
+}
+
+/// Migrates a new SQLite database in a temporary directory, removed along with the directory.
+#[cfg(all(test, feature = "sqlite"))]
+pub fn open_test_pool() -> Result<(tempfile::TempDir, Pool), WarehouseError> {
+    let directory = tempfile::tempdir()?;
+    let url = directory
+        .path()
+        .join("warehouse.db")
+        .to_string_lossy()
+        .into_owned();
+    run_pending_migrations(&url)?;
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(diesel::r2d2::ConnectionManager::new(url))?;
+    Ok((directory, pool))
+}
+
+/// Runs the pending migrations and fills the data older versions did not store. Returns whether an
+/// existing database just got repository architectures, its `any` packages being still to link.
+pub fn run_pending_migrations(database_url: &str) -> Result<bool, RunPendingMigrationsError> {
     if cfg!(feature = "sqlite") {
         if let Some(parent) = Path::new(database_url).parent() {
             fs::create_dir_all(parent)?;
         }
     }
     let connection: Connection = diesel::Connection::establish(database_url)?;
+    diesel_migrations::setup_database(&connection)?;
+    let previous_versions = connection.previously_run_migration_versions()?;
     let mut output = Vec::new();
     embedded_migrations::run_with_output(&connection, &mut output)?;
+    #[cfg(any(feature = "mysql", feature = "postgres"))]
+    search_migrations::embedded_migrations::run_with_output(&connection, &mut output)?;
+    Dependency::split_constraints(&connection)?;
+    File::fill_basenames(&connection)?;
     let output = String::from_utf8(output)?;
     for line in output.lines() {
         info!("{}", line);
     }
-    Ok(())
+    Ok(!previous_versions.is_empty() && !previous_versions.contains(ARCHITECTURES_MIGRATION))
 }
@@ -15,6 +15,8 @@ table! {
         extension -> Text,
         repository_id -> Text,
         maintainer_id -> Text,
+        sha256sum -> Text,
+        md5sum -> Text,
     }
 }
 
@@ -23,6 +25,21 @@ table! {
         id -> Text,
         name -> Text,
         package_id -> Text,
+        kind -> Text,
+        operator -> Text,
+        version -> Text,
+        description -> Text,
+    }
+}
+
+table! {
+    warehouse_package_download (id) {
+        id -> Text,
+        day -> Date,
+        version -> Text,
+        architecture -> Text,
+        count -> BigInt,
+        package_id -> Text,
     }
 }
 
@@ -32,6 +49,7 @@ table! {
         name -> Text,
         size -> BigInt,
         package_id -> Text,
+        basename -> Text,
     }
 }
 
@@ -42,6 +60,9 @@ table! {
         version -> Text,
         maintainer_id -> Text,
         package_id -> Text,
+        extension -> Text,
+        sha256sum -> Text,
+        md5sum -> Text,
     }
 }
 
@@ -50,6 +71,14 @@ table! {
         id -> Text,
         name -> Text,
         extension -> Text,
+        require_signature -> Bool,
+        description -> Text,
+        architectures -> Text,
+        retention -> Integer,
+        depends_on -> Text,
+        strict_dependencies -> Bool,
+        snapshot_interval -> Integer,
+        snapshot_retention -> Integer,
     }
 }
 
@@ -64,16 +93,32 @@ table! {
     }
 }
 
+table! {
+    warehouse_user_token (id) {
+        id -> Text,
+        creation_date -> Timestamp,
+        name -> Text,
+        hash -> Text,
+        user_id -> Text,
+    }
+}
+
 joinable!(warehouse_package -> warehouse_repository (repository_id));
 joinable!(warehouse_package -> warehouse_user (maintainer_id));
 joinable!(warehouse_package_dependency -> warehouse_package (package_id));
+joinable!(warehouse_package_download -> warehouse_package (package_id));
 joinable!(warehouse_package_file -> warehouse_package (package_id));
 joinable!(warehouse_package_version -> warehouse_user (maintainer_id));
 joinable!(warehouse_package_version -> warehouse_package (package_id));
+joinable!(warehouse_user_token -> warehouse_user (user_id));
 
 allow_tables_to_appear_in_same_query!(
     warehouse_package,
+    warehouse_package_dependency,
+    warehouse_package_download,
+    warehouse_package_file,
     warehouse_repository,
     warehouse_user,
-    warehouse_package_version
+    warehouse_package_version,
+    warehouse_user_token
 );
@@ -0,0 +1,90 @@
+use crate::database::Backend;
+use diesel::{
+    expression::{AppearsOnTable, Expression, NonAggregate, SelectableExpression},
+    query_builder::{AstPass, QueryFragment, QueryId},
+    result::QueryResult,
+    sql_types::{Bool, Text},
+};
+
+sql_function!(fn lower(value: Text) -> Text);
+
+/// Matches the name and description of a package against a full-text query.
+pub struct FullTextMatch {
+    query: String,
+}
+
+impl Expression for FullTextMatch {
+    type SqlType = Bool;
+}
+
+impl NonAggregate for FullTextMatch {}
+
+impl<QS> AppearsOnTable<QS> for FullTextMatch {}
+
+impl<QS> SelectableExpression<QS> for FullTextMatch {}
+
+impl QueryFragment<Backend> for FullTextMatch {
+    #[cfg(feature = "mysql")]
+    fn walk_ast(&self, mut out: AstPass<Backend>) -> QueryResult<()> {
+        out.push_sql("MATCH (warehouse_package.name, warehouse_package.description) AGAINST (");
+        out.push_bind_param::<Text, _>(&self.query)?;
+        out.push_sql(" IN NATURAL LANGUAGE MODE)");
+        Ok(())
+    }
+
+    #[cfg(feature = "postgres")]
+    fn walk_ast(&self, mut out: AstPass<Backend>) -> QueryResult<()> {
+        out.push_sql(
+            "to_tsvector('simple', warehouse_package.name || ' ' || warehouse_package.description) \
+             @@ plainto_tsquery('simple', ",
+        );
+        out.push_bind_param::<Text, _>(&self.query)?;
+        out.push_sql(")");
+        Ok(())
+    }
+
+    #[cfg(feature = "sqlite")]
+    fn walk_ast(&self, mut out: AstPass<Backend>) -> QueryResult<()> {
+        out.push_sql("warehouse_package.description LIKE ");
+        out.push_bind_param::<Text, _>(&like_pattern(&self.query))?;
+        out.push_sql(" ESCAPE '\\'");
+        Ok(())
+    }
+}
+
+impl QueryId for FullTextMatch {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+pub fn full_text_match(query: &str) -> FullTextMatch {
+    FullTextMatch {
+        query: query.to_string(),
+    }
+}
+
+/// Translates a shell glob (`*` and `?`) into a pattern to be used with `ESCAPE '\'`.
+pub fn glob_pattern(glob: &str) -> String {
+    glob.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+        .replace('*', "%")
+        .replace('?', "_")
+}
+
+pub fn is_glob(value: &str) -> bool {
+    value.contains(['*', '?'])
+}
+
+/// Builds a case-insensitive substring pattern to be used with `ESCAPE '\'`.
+pub fn like_pattern(query: &str) -> String {
+    format!(
+        "%{}%",
+        query
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
@@ -6,21 +6,24 @@ extern crate diesel_migrations;
 use crate::{
     core::{
         config::Config,
-        error::{self, WarehouseResult},
+        error::{self, WarehouseError, WarehouseResult},
         logger,
     },
-    database::Pool,
-    utils::{auth::AuthenticationService, path, regex::Regexes},
+    database::{Pool, PooledConnection},
+    utils::{
+        auth::AuthenticationService, download::DownloadCounter, fsck, path, regex::Regexes,
+        repository, snapshot, storage,
+    },
 };
 use actix_identity::{CookieIdentityPolicy, IdentityService};
 use actix_web::{
     http::StatusCode,
     middleware::{errhandlers::ErrorHandlers, Logger},
-    App, FromRequest, HttpServer,
+    web, App, FromRequest, HttpServer,
 };
 use awmp::Parts;
 use diesel::r2d2::ConnectionManager;
-use log::{debug, info};
+use log::{debug, error, info};
 use std::{env, net::SocketAddr, process, time::Duration};
 use tera::Tera;
 
@@ -49,12 +52,16 @@ async fn main() -> WarehouseResult {
     // Prints the configuration
     debug!("{:?}", config.database);
     debug!("{:?}", config.logger);
+    debug!("{:?}", config.resolver);
     debug!("{:?}", config.server);
     debug!("{:?}", config.session);
+    debug!("{:?}", config.signing);
+    debug!("{:?}", config.storage);
     debug!("{:?}", config.ui);
+    debug!("{:?}", config.verification);
 
     // Runs pending database migrations
-    database::run_pending_migrations(&config.database.url)?;
+    let is_architectures_upgrade = database::run_pending_migrations(&config.database.url)?;
 
     // Constructs the application
     let tera = Tera::new(&format!("{}/**/*", path::templates_dir().display()))?;
@@ -66,10 +73,62 @@ async fn main() -> WarehouseResult {
         .max_size(config.database.pool_max_size)
         .min_idle(Some(config.database.pool_min_idle))
         .build(ConnectionManager::new(config.database.url.clone()))?;
+    let storage = storage::open(&config.storage)?;
+
+    // Publishes the `any` packages in every architecture of the repositories just upgraded
+    if is_architectures_upgrade {
+        let connection = PooledConnection::get(&pool)?;
+        repository::link_any_packages(&connection, &config, &storage)?;
+    }
+
+    // Runs a maintenance command instead of the server when one is given
+    if let Some(command) = env::args().nth(1) {
+        if command != "fsck" {
+            return Err(WarehouseError::UnknownCommand(command));
+        }
+        let repair = env::args().skip(2).any(|argument| argument == "--repair");
+        let issues = fsck::check(&PooledConnection::get(&pool)?, &config, &storage, repair)?;
+        for issue in &issues {
+            println!(
+                "{}{}",
+                issue,
+                if issue.repaired { " (repaired)" } else { "" }
+            );
+        }
+        println!("{} issue(s) found", issues.len());
+        if !repair && !issues.is_empty() {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
     let secret_key = base64::decode(&config.session.secret_key)?;
     let address = SocketAddr::new(config.server.ip_address, config.server.port);
     let workers = config.server.workers;
 
+    // Takes scheduled snapshots every hour
+    let snapshot_pool = pool.clone();
+    let snapshot_storage = storage.clone();
+    actix_rt::spawn(async move {
+        let mut interval = actix_rt::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            let pool = snapshot_pool.clone();
+            let storage = snapshot_storage.clone();
+            let result = web::block(move || -> WarehouseResult {
+                let connection = PooledConnection::get(&pool)?;
+                Ok(snapshot::take_scheduled_snapshots(&connection, &storage)?)
+            })
+            .await;
+            if let Err(error) = result {
+                error!("Error while taking scheduled snapshots: {}", error);
+            }
+        }
+    });
+
+    // Counts package downloads in the background
+    let downloads = DownloadCounter::start(pool.clone());
+
     // Starts the HTTP server
     info!("Starting server on {}", address);
     HttpServer::new(move || {
@@ -81,6 +140,8 @@ async fn main() -> WarehouseResult {
             .data(tera.clone())
             .data(regexes.clone())
             .data(pool.clone())
+            .data(storage.clone())
+            .data(downloads.clone())
             .wrap(Logger::new(&config.logger.access_format))
             .wrap(
                 ErrorHandlers::new()
@@ -1,9 +1,65 @@
-use crate::{core::error::WarehouseResult, utils::auth::Authentication, view};
-use actix_web::{HttpRequest, HttpResponse};
+use crate::{
+    core::{config::Config, error::WarehouseResult},
+    database::PooledConnection,
+    service::{package::model::Package, repository::model::Repository},
+    utils::{auth::Authentication, fsck, snapshot, storage::Storage},
+    view,
+};
+use actix_web::{
+    web::{self, Data},
+    HttpRequest, HttpResponse,
+};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+const MOST_DOWNLOADED_COUNT: usize = 10;
+const MOST_DOWNLOADED_DAYS: i64 = 30;
+
+pub async fn handle_fsck_post(
+    auth: Authentication,
+    config: Data<Config>,
+    connection: PooledConnection,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+) -> WarehouseResult<HttpResponse> {
+    let issues = web::block(move || fsck::check(&connection, &config, &storage, true)).await?;
+    view!(&request, "route/fsck", [
+        "user" => &auth.user(),
+        "issues" => &issues,
+        "repaired" => &true
+    ])
+}
 
 pub async fn serve_admin_page(
     auth: Authentication,
+    connection: PooledConnection,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+) -> WarehouseResult<HttpResponse> {
+    let snapshots = web::block(move || snapshot::list_snapshots(&storage)).await?;
+    view!(&request, "route/admin", [
+        "user" => &auth.user(),
+        "repositories" => &Repository::list(&connection)?,
+        "snapshots" => &snapshots,
+        "most_downloaded" => &Package::list_most_downloaded(
+            &connection,
+            Utc::now().naive_utc().date() - Duration::days(MOST_DOWNLOADED_DAYS - 1),
+            MOST_DOWNLOADED_COUNT,
+        )?
+    ])
+}
+
+pub async fn serve_fsck_page(
+    auth: Authentication,
+    config: Data<Config>,
+    connection: PooledConnection,
     request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
 ) -> WarehouseResult<HttpResponse> {
-    view!(&request, "route/admin", ["user" => &auth.user()])
+    let issues = web::block(move || fsck::check(&connection, &config, &storage, false)).await?;
+    view!(&request, "route/fsck", [
+        "user" => &auth.user(),
+        "issues" => &issues,
+        "repaired" => &false
+    ])
 }
@@ -0,0 +1,293 @@
+use crate::{
+    core::{
+        config::Config,
+        error::{
+            ErrorResponse, ImportPackageError, ReadPackageError, VerifySignatureError,
+            WarehouseError, WarehouseResult,
+        },
+    },
+    database::PooledConnection,
+    service::{
+        package::{
+            controller::{PackageListQuery, PackagePath},
+            model::{Dependency, File, Package, PackageFilter, Version},
+        },
+        repository::model::Repository,
+        user::model::User,
+    },
+    utils::{package, storage::Storage},
+};
+use actix_web::{
+    error::BlockingError,
+    http::StatusCode,
+    web::{self, Data, Path, Query},
+    HttpRequest, HttpResponse,
+};
+use awmp::Parts;
+use chrono::NaiveDateTime;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub struct ImportResponse {
+    file: String,
+    status: u16,
+    unmet_dependencies: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PackageDetailResponse<'a> {
+    #[serde(flatten)]
+    package: PackageResponse<'a>,
+    files: Vec<File>,
+    dependencies: Vec<Dependency>,
+    versions: Vec<VersionResponse>,
+}
+
+#[derive(Serialize)]
+pub struct PackageListResponse<'a> {
+    page: i32,
+    page_count: i64,
+    packages: Vec<PackageResponse<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct PackageResponse<'a> {
+    #[serde(flatten)]
+    package: &'a Package,
+    repository: &'a str,
+    maintainer: UserResponse,
+}
+
+impl<'a> From<&'a (Package, Repository, User)> for PackageResponse<'a> {
+    fn from((package, repository, user): &'a (Package, Repository, User)) -> Self {
+        PackageResponse {
+            package,
+            repository: &repository.name,
+            maintainer: UserResponse::from(user),
+        }
+    }
+}
+
+/// Public part of a user; the email address and password hash are never exposed.
+#[derive(Serialize)]
+pub struct UserResponse {
+    name: String,
+    creation_date: NaiveDateTime,
+    admin: bool,
+}
+
+impl From<&User> for UserResponse {
+    fn from(user: &User) -> Self {
+        UserResponse {
+            name: user.name.clone(),
+            creation_date: user.creation_date,
+            admin: user.admin,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UserPath {
+    name: String,
+}
+
+#[derive(Serialize)]
+pub struct UserProfileResponse<'a> {
+    #[serde(flatten)]
+    user: UserResponse,
+    packages: Vec<PackageResponse<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct VersionResponse {
+    #[serde(flatten)]
+    version: Version,
+    maintainer: UserResponse,
+}
+
+pub async fn fetch_package(
+    connection: PooledConnection,
+    path: Path<PackagePath>,
+) -> WarehouseResult<HttpResponse> {
+    let package = match Package::find_by_name_repository_and_architecture(
+        &connection,
+        &path.name,
+        &path.repository,
+        &path.architecture,
+    )? {
+        Some(package) => package,
+        None => return Ok(not_found()),
+    };
+    Ok(HttpResponse::Ok().json(PackageDetailResponse {
+        files: package.0.list_files(&connection)?,
+        dependencies: package.0.list_dependencies(&connection)?,
+        versions: package
+            .0
+            .list_versions(&connection)?
+            .into_iter()
+            .map(|(version, user)| VersionResponse {
+                version,
+                maintainer: UserResponse::from(&user),
+            })
+            .collect(),
+        package: PackageResponse::from(&package),
+    }))
+}
+
+pub async fn fetch_user(
+    connection: PooledConnection,
+    path: Path<UserPath>,
+) -> WarehouseResult<HttpResponse> {
+    let user = match User::find_by_name(&connection, &path.name)? {
+        Some(user) => user,
+        None => return Ok(not_found()),
+    };
+    let filter = PackageFilter {
+        maintainer: &user.name,
+        ..PackageFilter::default()
+    };
+    let packages = Package::list(
+        &connection,
+        &filter,
+        0,
+        Package::count(&connection, &filter)?,
+    )?;
+    Ok(HttpResponse::Ok().json(UserProfileResponse {
+        user: UserResponse::from(&user),
+        packages: packages.iter().map(PackageResponse::from).collect(),
+    }))
+}
+
+pub async fn handle_upload_packages_post(
+    config: Data<Config>,
+    connection: PooledConnection,
+    parts: Parts,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    let checksums = package::expected_checksums(&request);
+    match web::block(move || {
+        package::import_packages(&connection, &config, &storage, parts, &checksums, &user)
+    })
+    .await
+    {
+        Ok(results) => {
+            let responses: Vec<ImportResponse> = results
+                .into_iter()
+                .map(|import| match import.result {
+                    Ok(unmet_dependencies) => ImportResponse {
+                        file: import.name,
+                        status: StatusCode::CREATED.as_u16(),
+                        unmet_dependencies,
+                        error: None,
+                    },
+                    Err(error) => ImportResponse {
+                        status: import_error_status(&error).as_u16(),
+                        unmet_dependencies: match &error {
+                            ImportPackageError::UnmetDependencies(dependencies) => {
+                                dependencies.clone()
+                            }
+                            _ => Vec::new(),
+                        },
+                        error: Some(import_error_message(&import.name, &error)),
+                        file: import.name,
+                    },
+                })
+                .collect();
+            let mut statuses = responses.iter().map(|response| response.status);
+            let status = match statuses.next() {
+                Some(first) if statuses.all(|status| status == first) => {
+                    StatusCode::from_u16(first).unwrap_or(StatusCode::MULTI_STATUS)
+                }
+                _ => StatusCode::MULTI_STATUS,
+            };
+            Ok(HttpResponse::build(status).json(responses))
+        }
+        Err(BlockingError::Error(error)) => Ok(HttpResponse::build(import_error_status(&error))
+            .json(ErrorResponse {
+                error: import_error_message("request", &error),
+            })),
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn import_error_message(name: &str, error: &ImportPackageError) -> String {
+    if import_error_status(error).is_server_error() {
+        error!("Error while importing {}: {:?}", name, error);
+        String::from("Internal server error")
+    } else {
+        error.to_string()
+    }
+}
+
+fn import_error_status(error: &ImportPackageError) -> StatusCode {
+    match error {
+        ImportPackageError::ArchitectureConflict(..)
+        | ImportPackageError::OlderPackageVersion { .. } => StatusCode::CONFLICT,
+        ImportPackageError::FileNotFound | ImportPackageError::TextFieldNotFound(..) => {
+            StatusCode::BAD_REQUEST
+        }
+        ImportPackageError::MultipartError(awmp::Error::FileTooLarge { .. }) => {
+            StatusCode::PAYLOAD_TOO_LARGE
+        }
+        ImportPackageError::MultipartError(..) => StatusCode::BAD_REQUEST,
+        ImportPackageError::ReadPackageError(ReadPackageError::InvalidPackage(..))
+        | ImportPackageError::ReadPackageError(ReadPackageError::Io(..)) => {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+        ImportPackageError::ReadPackageError(ReadPackageError::UnsupportedFileType) => {
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        }
+        ImportPackageError::RepositoryNotFound(..) => StatusCode::NOT_FOUND,
+        ImportPackageError::ChecksumMismatch { .. }
+        | ImportPackageError::SignatureRequired
+        | ImportPackageError::UnsupportedArchitecture(..)
+        | ImportPackageError::VerifySignatureError(VerifySignatureError::PgpError(..)) => {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+        ImportPackageError::UnauthorizedUpdate
+        | ImportPackageError::VerifySignatureError(VerifySignatureError::UnknownKey) => {
+            StatusCode::FORBIDDEN
+        }
+        ImportPackageError::UnmetDependencies(..) => StatusCode::FAILED_DEPENDENCY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(ErrorResponse {
+        error: String::from("Not Found"),
+    })
+}
+
+pub async fn serve_package_list(
+    config: Data<Config>,
+    connection: PooledConnection,
+    query: Query<PackageListQuery>,
+) -> WarehouseResult<HttpResponse> {
+    if query.is_valid() {
+        let offset = (i64::from(query.page) - 1) * i64::from(config.ui.paging_num);
+        let limit = i64::from(config.ui.paging_num);
+        let filter = query.filter();
+        let packages = Package::list(&connection, &filter, offset, limit)?;
+        let page_count = (Package::count(&connection, &filter)? as f64
+            / f64::from(config.ui.paging_num))
+        .ceil() as i64;
+        Ok(HttpResponse::Ok().json(PackageListResponse {
+            page: query.page,
+            page_count,
+            packages: packages.iter().map(PackageResponse::from).collect(),
+        }))
+    } else {
+        Err(WarehouseError::InvalidPathData)
+    }
+}
+
+pub async fn serve_repository_list(connection: PooledConnection) -> WarehouseResult<HttpResponse> {
+    Ok(HttpResponse::Ok().json(Repository::list(&connection)?))
+}
@@ -1,6 +1,9 @@
-use crate::{core::error::WarehouseResult, utils::path};
+use crate::{
+    core::{config::Config, error::WarehouseResult},
+    utils::{path, signature},
+};
 use actix_files::NamedFile;
-use actix_web::{http::header::LOCATION, HttpResponse, Result};
+use actix_web::{http::header::LOCATION, web::Data, HttpResponse, Result};
 
 pub async fn serve_favicon() -> Result<NamedFile> {
     Ok(NamedFile::open(&format!(
@@ -14,3 +17,14 @@ pub async fn serve_index_page() -> WarehouseResult<HttpResponse> {
         .header(LOCATION, "/package/list")
         .finish())
 }
+
+pub async fn serve_signing_key(config: Data<Config>) -> WarehouseResult<HttpResponse> {
+    if config.signing.enabled {
+        let key = signature::load_secret_key(&config.signing.private_key)?;
+        Ok(HttpResponse::Ok()
+            .content_type("application/pgp-keys")
+            .body(signature::export_public_key(&key)?))
+    } else {
+        Ok(HttpResponse::NotFound().into())
+    }
+}
@@ -6,9 +6,12 @@ use actix_files::Files;
 use actix_web::web::{self, ServiceConfig};
 
 pub mod admin;
+pub mod api;
 pub mod index;
 pub mod package;
 pub mod repository;
+pub mod rpc;
+pub mod snapshot;
 pub mod user;
 
 pub fn configure(config: &mut ServiceConfig) {
@@ -17,25 +20,110 @@ pub fn configure(config: &mut ServiceConfig) {
     let redirect_if_authenticated = RedirectIfAuthenticated::new("/");
     config
         .service(Files::new("/static", path::static_files_dir()))
+        .service(
+            web::scope("/api/v1")
+                .service(
+                    web::resource("/packages")
+                        .route(web::get().to(api::controller::serve_package_list))
+                        .route(web::post().to(api::controller::handle_upload_packages_post)),
+                )
+                .service(
+                    web::resource("/packages/{repository}/{architecture}/{name}")
+                        .route(web::get().to(api::controller::fetch_package)),
+                )
+                .service(
+                    web::resource("/repositories")
+                        .route(web::get().to(api::controller::serve_repository_list)),
+                )
+                .service(
+                    web::resource("/users/{name}")
+                        .route(web::get().to(api::controller::fetch_user)),
+                ),
+        )
+        .service(
+            web::resource(vec!["/rpc", "/rpc/"]).route(web::get().to(rpc::controller::serve_rpc)),
+        )
         .service(web::resource("/").route(web::get().to(index::controller::serve_index_page)))
         .service(
             web::resource("/favicon.ico").route(web::get().to(index::controller::serve_favicon)),
         )
+        .service(
+            web::resource("/signing_key.asc")
+                .route(web::get().to(index::controller::serve_signing_key)),
+        )
         .service(
             web::resource("/admin")
                 .wrap(authorize.clone())
                 .wrap(authenticate.clone())
                 .route(web::get().to(admin::controller::serve_admin_page)),
         )
+        .service(
+            web::resource("/admin/bootstrap")
+                .wrap(authorize.clone())
+                .wrap(authenticate.clone())
+                .route(web::get().to(package::controller::serve_bootstrap_page))
+                .route(web::post().to(package::controller::handle_bootstrap_post)),
+        )
+        .service(
+            web::resource("/admin/fsck")
+                .wrap(authorize.clone())
+                .wrap(authenticate.clone())
+                .route(web::get().to(admin::controller::serve_fsck_page))
+                .route(web::post().to(admin::controller::handle_fsck_post)),
+        )
+        .service(
+            web::scope("/admin/repository")
+                .wrap(authorize.clone())
+                .wrap(authenticate.clone())
+                .service(
+                    web::resource("/create")
+                        .route(web::get().to(repository::controller::serve_create_repository_page))
+                        .route(
+                            web::post().to(repository::controller::handle_create_repository_post),
+                        ),
+                )
+                .service(
+                    web::resource("/{name}/delete")
+                        .route(web::post().to(repository::controller::delete_repository)),
+                )
+                .service(
+                    web::resource("/{name}/update")
+                        .route(web::get().to(repository::controller::serve_update_repository_page))
+                        .route(
+                            web::post().to(repository::controller::handle_update_repository_post),
+                        ),
+                ),
+        )
+        .service(
+            web::scope("/admin/snapshot")
+                .wrap(authorize.clone())
+                .wrap(authenticate.clone())
+                .service(
+                    web::resource("/create")
+                        .route(web::post().to(snapshot::controller::handle_create_snapshot_post)),
+                )
+                .service(
+                    web::resource("/{date:\\d\\d\\d\\d-\\d\\d-\\d\\d}/{repository}/delete")
+                        .route(web::post().to(snapshot::controller::delete_snapshot)),
+                ),
+        )
         .service(
             web::scope("/package")
+                .service(
+                    web::resource(
+                        "/{repository}/{architecture}/{name}.{extension:pkg\\.tar.*}.sig",
+                    )
+                    .route(web::get().to(package::controller::serve_package_signature)),
+                )
                 .service(
                     web::resource("/{repository}/{architecture}/{name}.{extension:pkg.*}")
                         .route(web::get().to(package::controller::serve_package_archive)),
                 )
                 .service(
-                    web::resource("/{repository}/{architecture}/{name}.{extension:(db|files)}")
-                        .route(web::get().to(package::controller::serve_repository_database)),
+                    web::resource(
+                        "/{repository}/{architecture}/{name}.{extension:(db|files)(\\.sig)?}",
+                    )
+                    .route(web::get().to(package::controller::serve_repository_database)),
                 )
                 .service(
                     web::resource("/{repository}/{architecture}/{name}")
@@ -46,9 +134,27 @@ pub fn configure(config: &mut ServiceConfig) {
                         .wrap(authorize)
                         .route(web::post().to(package::controller::delete_package)),
                 )
+                .service(
+                    web::resource("/{repository}/{architecture}/{name}/promote")
+                        .wrap(authenticate.clone())
+                        .route(web::post().to(package::controller::handle_promote_package_post)),
+                )
+                .service(
+                    web::resource("/{repository}/{architecture}/{name}/rollback")
+                        .wrap(authenticate.clone())
+                        .route(web::post().to(package::controller::handle_rollback_package_post)),
+                )
+                .service(
+                    web::resource("/feed.{format:(atom|rss)}")
+                        .route(web::get().to(package::controller::serve_package_feed)),
+                )
+                .service(
+                    web::resource("/files/search")
+                        .route(web::get().to(package::controller::serve_file_search_page)),
+                )
                 .service(
                     web::resource("/import")
-                        .wrap(authenticate)
+                        .wrap(authenticate.clone())
                         .route(web::get().to(package::controller::serve_import_package_page))
                         .route(web::post().to(package::controller::handle_import_package_post)),
                 )
@@ -57,6 +163,19 @@ pub fn configure(config: &mut ServiceConfig) {
                         .route(web::get().to(package::controller::serve_package_list_page)),
                 ),
         )
+        .service(
+            web::scope("/snapshot/{date:\\d\\d\\d\\d-\\d\\d-\\d\\d}")
+                .service(
+                    web::resource("/{repository}/{architecture}/{name}.{extension:pkg.*}")
+                        .route(web::get().to(snapshot::controller::serve_snapshot_archive)),
+                )
+                .service(
+                    web::resource(
+                        "/{repository}/{architecture}/{name}.{extension:(db|files)(\\.sig)?}",
+                    )
+                    .route(web::get().to(snapshot::controller::serve_snapshot_database)),
+                ),
+        )
         .service(
             web::scope("/user")
                 .service(
@@ -74,6 +193,17 @@ pub fn configure(config: &mut ServiceConfig) {
                         .wrap(redirect_if_authenticated)
                         .route(web::get().to(user::controller::serve_sign_up_page))
                         .route(web::post().to(user::controller::handle_sign_up_post)),
+                )
+                .service(
+                    web::resource("/tokens")
+                        .wrap(authenticate.clone())
+                        .route(web::get().to(user::controller::serve_tokens_page))
+                        .route(web::post().to(user::controller::handle_create_token_post)),
+                )
+                .service(
+                    web::resource("/tokens/{id}/delete")
+                        .wrap(authenticate)
+                        .route(web::post().to(user::controller::handle_delete_token_post)),
                 ),
         );
 }
@@ -1,40 +1,177 @@
 use crate::{
     core::{
         config::Config,
-        error::{ImportPackageError, ReadPackageError, WarehouseError, WarehouseResult},
+        error::{
+            ImportPackageError, PromotePackageError, ReadPackageError, VerifySignatureError,
+            WarehouseError, WarehouseResult,
+        },
     },
     database::PooledConnection,
-    service::{package::model::Package, repository::model::Repository, user::model::User},
-    utils::{auth::Authentication, package, path},
+    service::{
+        package::model::{Download, File, Package, PackageFilter, Version},
+        repository::model::Repository,
+        user::model::User,
+    },
+    utils::{
+        auth::Authentication,
+        download::DownloadCounter,
+        feed::Feed,
+        package::{self, ImportResult},
+        path,
+        storage::Storage,
+    },
     view,
 };
-use actix_files::NamedFile;
 use actix_web::{
-    http::header::LOCATION,
-    web::{Data, Path, Query},
+    http::{
+        header::{ACCEPT, LOCATION},
+        StatusCode,
+    },
+    web::{self, Data, Form, Path, Query},
     HttpRequest, HttpResponse, Result,
 };
 use awmp::Parts;
-use serde::Deserialize;
-use std::fs::{self};
+use chrono::{Duration, NaiveDate, Utc};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+use url::form_urlencoded;
+
+const DOWNLOAD_TREND_DAYS: i64 = 30;
+
+const FEED_ENTRY_COUNT: i64 = 50;
+
+const PACKAGE_SORTS: [(&str, &str); 5] = [
+    ("", "Relevance"),
+    ("name", "Name"),
+    ("build_date", "Build date"),
+    ("modification_date", "Last updated"),
+    ("size", "Size"),
+];
+
+#[derive(Deserialize, Serialize)]
+pub struct BootstrapForm {
+    directory: String,
+    repository: String,
+    maintainer: String,
+}
+
+#[derive(Serialize)]
+pub struct DownloadDay {
+    day: NaiveDate,
+    count: i64,
+    height: i64,
+}
 
 #[derive(Deserialize)]
+pub struct FeedPath {
+    format: String,
+}
+
+#[derive(Default, Deserialize)]
 #[serde(default)]
-pub struct PackageListQuery {
+pub struct FeedQuery {
+    repository: String,
+    architecture: String,
+    maintainer: String,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct FileSearchQuery {
     page: i32,
+    q: String,
+}
+
+impl Default for FileSearchQuery {
+    fn default() -> FileSearchQuery {
+        FileSearchQuery {
+            page: 1,
+            q: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FileSearchResult<'a> {
+    path: &'a str,
+    name: &'a str,
+    version: &'a str,
+    architecture: &'a str,
+    repository: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct PackageListQuery {
+    pub page: i32,
+    q: String,
+    repository: String,
+    architecture: String,
+    maintainer: String,
+    sort: String,
+    order: String,
 }
 
 impl Default for PackageListQuery {
     fn default() -> PackageListQuery {
-        PackageListQuery { page: 1 }
+        PackageListQuery {
+            page: 1,
+            q: String::new(),
+            repository: String::new(),
+            architecture: String::new(),
+            maintainer: String::new(),
+            sort: String::new(),
+            order: String::new(),
+        }
+    }
+}
+
+impl PackageListQuery {
+    pub fn filter(&self) -> PackageFilter<'_> {
+        PackageFilter {
+            query: self.q.trim(),
+            repository: &self.repository,
+            architecture: &self.architecture,
+            maintainer: self.maintainer.trim(),
+            sort: &self.sort,
+            descending: self.order == "desc",
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.page > 0
+            && PACKAGE_SORTS.iter().any(|(sort, _)| *sort == self.sort)
+            && ["", "asc", "desc"].contains(&self.order.as_str())
     }
 }
 
 #[derive(Deserialize)]
 pub struct PackagePath {
+    pub repository: String,
+    pub architecture: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct PromoteForm {
     repository: String,
-    architecture: String,
-    name: String,
+    #[serde(default)]
+    keep: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RollbackForm {
+    version: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeletePackageQuery {
+    #[serde(default)]
+    force: bool,
 }
 
 #[derive(Deserialize)]
@@ -46,8 +183,11 @@ pub struct PackageFilePath {
 }
 
 pub async fn delete_package(
+    config: Data<Config>,
     connection: PooledConnection,
     path: Path<PackagePath>,
+    query: Query<DeletePackageQuery>,
+    storage: Data<Arc<dyn Storage>>,
 ) -> WarehouseResult<HttpResponse> {
     match Package::find_by_name_repository_and_architecture(
         &connection,
@@ -56,29 +196,69 @@ pub async fn delete_package(
         &path.architecture,
     )? {
         Some((package, repository, _)) => {
-            package::remove_package_from_repository(
-                &path.name,
-                &path::repository_file(&path.repository, &path.architecture, &repository.extension),
-            )?;
-            let package_path = path::package_file(
-                &path.repository,
-                &path.architecture,
-                &path.name,
-                &package.extension,
-            );
-            if package_path.exists() {
-                fs::remove_file(&package_path)?;
+            if !query.force
+                && !package
+                    .list_reverse_dependencies(&connection, "depend")?
+                    .is_empty()
+            {
+                return Ok(HttpResponse::Conflict().into());
             }
-            package.delete_versions(&connection)?;
-            package.delete_dependencies(&connection)?;
-            package.delete_files(&connection)?;
-            package.delete(&connection)?;
+            web::block(move || {
+                package::delete_package(&connection, &config, &storage, &repository, &package)
+            })
+            .await?;
             Ok(HttpResponse::Ok().into())
         }
         None => Ok(HttpResponse::NotFound().into()),
     }
 }
 
+/// Returns the downloads of the last days, with bar heights relative to the busiest one.
+fn download_trend(downloads: &[Download], today: NaiveDate) -> Vec<DownloadDay> {
+    let mut days: Vec<DownloadDay> = (0..DOWNLOAD_TREND_DAYS)
+        .rev()
+        .map(|offset| {
+            let day = today - Duration::days(offset);
+            DownloadDay {
+                day,
+                count: downloads
+                    .iter()
+                    .filter(|download| download.day == day)
+                    .map(|download| download.count)
+                    .sum(),
+                height: 0,
+            }
+        })
+        .collect();
+    let maximum = days.iter().map(|day| day.count).max().unwrap_or_default();
+    if maximum > 0 {
+        for day in &mut days {
+            day.height = day.count * 100 / maximum;
+        }
+    }
+    days
+}
+
+/// Returns the query string selecting the feed of the packages matching a filter.
+fn feed_query(filter: &PackageFilter) -> String {
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in &[
+        ("repository", filter.repository),
+        ("architecture", filter.architecture),
+        ("maintainer", filter.maintainer),
+    ] {
+        if !value.is_empty() {
+            serializer.append_pair(key, value);
+        }
+    }
+    let query = serializer.finish();
+    if query.is_empty() {
+        query
+    } else {
+        format!("?{}", query)
+    }
+}
+
 pub async fn fetch_package(
     auth: Authentication,
     connection: PooledConnection,
@@ -91,54 +271,426 @@ pub async fn fetch_package(
         &path.repository,
         &path.architecture,
     )? {
-        Some(package) => view!(&request, "route/package/detail", [
-            "user" => &auth.user(),
-            "package" => &package,
-            "files" => &package.0.list_files(&connection)?,
-            "dependencies" => &package.0.list_dependencies(&connection)?,
-            "versions" => &package.0.list_versions(&connection)?
-        ]),
+        Some(package) => render_package_page(&connection, &package, &request, auth.user(), None),
         None => Ok(HttpResponse::NotFound().into()),
     }
 }
 
+pub async fn handle_bootstrap_post(
+    config: Data<Config>,
+    connection: PooledConnection,
+    form: Form<BootstrapForm>,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    let directory = std::path::Path::new(form.directory.trim());
+    let repository = Repository::find_by_name(&connection, &form.repository)?
+        .ok_or(WarehouseError::InvalidFormData)?;
+    let maintainer =
+        User::find_by_id(&connection, &form.maintainer)?.ok_or(WarehouseError::InvalidFormData)?;
+    if !directory.is_dir() {
+        return view!(&request, "route/package/bootstrap", [
+            "user" => &user,
+            "repositories" => &Repository::list(&connection)?,
+            "users" => &User::list(&connection)?,
+            "form" => &*form,
+            "error" => "The directory does not exist."
+        ]);
+    }
+    let directory = directory.to_path_buf();
+    let (connection, result) = web::block(move || -> WarehouseResult<_> {
+        let result = package::bootstrap_repository(
+            &connection,
+            &config,
+            &storage,
+            &directory,
+            &repository,
+            &maintainer,
+        );
+        Ok((connection, result))
+    })
+    .await?;
+    match result {
+        Ok(results) => view!(&request, "route/package/bootstrap", [
+            "user" => &user,
+            "repositories" => &Repository::list(&connection)?,
+            "users" => &User::list(&connection)?,
+            "form" => &*form,
+            "results" => &import_result_messages(&results)
+        ]),
+        Err(error) => match import_error_message(&error) {
+            Some(message) => view!(&request, "route/package/bootstrap", [
+                "user" => &user,
+                "repositories" => &Repository::list(&connection)?,
+                "users" => &User::list(&connection)?,
+                "form" => &*form,
+                "error" => &message
+            ]),
+            None => Err(error.into()),
+        },
+    }
+}
+
 pub async fn handle_import_package_post(
+    config: Data<Config>,
     connection: PooledConnection,
     parts: Parts,
     request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    let checksums = package::expected_checksums(&request);
+    let (connection, user, result) = web::block(move || -> WarehouseResult<_> {
+        let result =
+            package::import_packages(&connection, &config, &storage, parts, &checksums, &user);
+        Ok((connection, user, result))
+    })
+    .await?;
+    match result {
+        Ok(results)
+            if results
+                .iter()
+                .all(|import| matches!(&import.result, Ok(unmet) if unmet.is_empty())) =>
+        {
+            Ok(HttpResponse::Found().header(LOCATION, "/").finish())
+        }
+        Ok(results) => view!(&request, "route/package/import", [
+            "user" => &user,
+            "repositories" => &Repository::list(&connection)?,
+            "results" => &import_result_messages(&results)
+        ]),
+        Err(error) => match import_error_message(&error) {
+            Some(message) => view!(&request, "route/package/import", [
+                "user" => &user,
+                "repositories" => &Repository::list(&connection)?,
+                "error" => &message
+            ]),
+            None => Err(error.into()),
+        },
+    }
+}
+
+pub async fn handle_promote_package_post(
+    config: Data<Config>,
+    connection: PooledConnection,
+    form: Form<PromoteForm>,
+    path: Path<PackagePath>,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
     user: User,
 ) -> WarehouseResult<HttpResponse> {
-    if let Err(error) = package::import_package(&connection, parts, &user) {
-        let error = match error {
-            ImportPackageError::MultipartError(awmp::Error::FileTooLarge { limit, .. }) => {
-                format!("File too large. Limited to {} bytes.", limit)
+    let package = match Package::find_by_name_repository_and_architecture(
+        &connection,
+        &path.name,
+        &path.repository,
+        &path.architecture,
+    )? {
+        Some(package) => package,
+        None => return Ok(HttpResponse::NotFound().into()),
+    };
+    if !user.admin && package.0.maintainer_id != user.id {
+        return Ok(HttpResponse::Unauthorized().into());
+    }
+    let target = Repository::find_by_name(&connection, &form.repository)?
+        .ok_or_else(|| ImportPackageError::RepositoryNotFound(form.repository.clone()))?;
+    let (connection, package, target, user, result) = web::block(move || -> WarehouseResult<_> {
+        let result = package::promote_package(
+            &connection,
+            &config,
+            &storage,
+            &package.1,
+            &package.0,
+            &target,
+            form.keep,
+            &user,
+        );
+        Ok((connection, package, target, user, result))
+    })
+    .await?;
+    match result {
+        Ok(_) => Ok(HttpResponse::Found()
+            .header(
+                LOCATION,
+                format!(
+                    "/package/{}/{}/{}",
+                    target.name, path.architecture, path.name
+                ),
+            )
+            .finish()),
+        Err(error) => {
+            let error = match error {
+                PromotePackageError::ImportPackageError(
+                    ImportPackageError::ArchitectureConflict(architecture),
+                ) => format!(
+                    "Package already exists in {} for the {} architecture.",
+                    target.name, architecture
+                ),
+                PromotePackageError::ImportPackageError(
+                    ImportPackageError::OlderPackageVersion { old, new },
+                ) => format!(
+                    "Package already exists in {} in a more recent version. {} <= {}.",
+                    target.name, new, old
+                ),
+                PromotePackageError::ImportPackageError(ImportPackageError::SignatureRequired) => {
+                    format!(
+                        "The {} repository requires a package signature.",
+                        target.name
+                    )
+                }
+                PromotePackageError::ImportPackageError(ImportPackageError::UnauthorizedUpdate) => {
+                    format!(
+                        "You are not the maintainer of the package in {}.",
+                        target.name
+                    )
+                }
+                PromotePackageError::ImportPackageError(
+                    ImportPackageError::UnsupportedArchitecture(architecture),
+                ) => format!(
+                    "The {} repository does not support the {} architecture.",
+                    target.name, architecture
+                ),
+                PromotePackageError::SameRepository => {
+                    String::from("The package is already in this repository.")
+                }
+                _ => return Err(error.into()),
+            };
+            render_package_page(&connection, &package, &request, Some(user), Some(&error))
+        }
+    }
+}
+
+pub async fn handle_rollback_package_post(
+    config: Data<Config>,
+    connection: PooledConnection,
+    form: Form<RollbackForm>,
+    path: Path<PackagePath>,
+    storage: Data<Arc<dyn Storage>>,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    match Package::find_by_name_repository_and_architecture(
+        &connection,
+        &path.name,
+        &path.repository,
+        &path.architecture,
+    )? {
+        Some((mut package, repository, _)) => {
+            if !user.admin && package.maintainer_id != user.id {
+                return Ok(HttpResponse::Unauthorized().into());
             }
-            ImportPackageError::ReadPackageError(ReadPackageError::AlpmError(
-                alpm::Error::PkgInvalid,
-            ))
-            | ImportPackageError::ReadPackageError(ReadPackageError::AlpmError(
-                alpm::Error::PkgOpen,
-            )) => String::from("Invalid package format."),
-            ImportPackageError::ReadPackageError(ReadPackageError::UnsupportedFileType) => {
-                String::from("Unsupported file type.")
+            web::block(move || {
+                package::rollback_package(
+                    &connection,
+                    &config,
+                    &storage,
+                    &repository,
+                    &mut package,
+                    &form.version,
+                    &user,
+                )
+            })
+            .await?;
+            Ok(HttpResponse::Found()
+                .header(
+                    LOCATION,
+                    format!(
+                        "/package/{}/{}/{}",
+                        path.repository, path.architecture, path.name
+                    ),
+                )
+                .finish())
+        }
+        None => Ok(HttpResponse::NotFound().into()),
+    }
+}
+
+fn import_error_message(error: &ImportPackageError) -> Option<String> {
+    Some(match error {
+        ImportPackageError::ArchitectureConflict(architecture) => format!(
+            "Package already exists for the {} architecture.",
+            architecture
+        ),
+        ImportPackageError::ArchiveNotFound => {
+            String::from("The archive listed in the repository database is missing.")
+        }
+        ImportPackageError::ChecksumMismatch { .. } => {
+            String::from("The package does not match the expected SHA-256 checksum.")
+        }
+        ImportPackageError::DatabaseNotFound(..) => {
+            String::from("No repository database was found in the directory.")
+        }
+        ImportPackageError::FileNotFound => String::from("No package file was sent."),
+        ImportPackageError::MultipartError(awmp::Error::FileTooLarge { limit, .. }) => {
+            format!("File too large. Limited to {} bytes.", limit)
+        }
+        ImportPackageError::ReadPackageError(ReadPackageError::InvalidPackage(..))
+        | ImportPackageError::ReadPackageError(ReadPackageError::Io(..)) => {
+            String::from("Invalid package format.")
+        }
+        ImportPackageError::ReadPackageError(ReadPackageError::UnsupportedFileType) => {
+            String::from("Unsupported file type.")
+        }
+        ImportPackageError::OlderPackageVersion { old, new } => format!(
+            "Package already exists in a more recent version. {} <= {}.",
+            new, old
+        ),
+        ImportPackageError::SignatureRequired => {
+            String::from("The repository requires a package signature.")
+        }
+        ImportPackageError::UnauthorizedUpdate => {
+            String::from("You are not the maintainer of the package.")
+        }
+        ImportPackageError::UnlistedArchive => {
+            String::from("Skipped, the archive is not listed in the repository database.")
+        }
+        ImportPackageError::UnmetDependencies(dependencies) => format!(
+            "The repository cannot satisfy the dependencies: {}.",
+            dependencies.join(", ")
+        ),
+        ImportPackageError::UnsupportedArchitecture(architecture) => format!(
+            "The repository does not support the {} architecture.",
+            architecture
+        ),
+        ImportPackageError::VerifySignatureError(VerifySignatureError::PgpError(..)) => {
+            String::from("Invalid package signature.")
+        }
+        ImportPackageError::VerifySignatureError(VerifySignatureError::UnknownKey) => {
+            String::from("The package is not signed by a trusted key.")
+        }
+        ImportPackageError::WriteRepositoryError(error) => {
+            format!("Unable to update the repository database. {}.", error)
+        }
+        _ => return None,
+    })
+}
+
+fn import_result_messages(results: &[ImportResult]) -> Vec<(&str, &str, String)> {
+    results
+        .iter()
+        .map(|import| match &import.result {
+            Ok(unmet_dependencies) if unmet_dependencies.is_empty() => {
+                (import.name.as_str(), "success", String::from("Imported."))
             }
-            ImportPackageError::OlderPackageVersion { old, new } => format!(
-                "Package already exists in a more recent version. {} <= {}.",
-                new, old
+            Ok(unmet_dependencies) => (
+                import.name.as_str(),
+                "warning",
+                format!(
+                    "Imported with unmet dependencies: {}.",
+                    unmet_dependencies.join(", ")
+                ),
             ),
-            ImportPackageError::UnauthorizedUpdate => {
-                String::from("You are not the maintainer of the package.")
-            }
-            _ => return Err(error.into()),
-        };
-        view!(&request, "route/package/import", [
-            "user" => &user,
-            "repositories" => &Repository::list(&connection)?,
-            "error" => &error
-        ])
+            Err(error) => (
+                import.name.as_str(),
+                "danger",
+                import_error_message(error).unwrap_or_else(|| {
+                    error!("Error while importing {}: {:?}", import.name, error);
+                    String::from("Unexpected error while importing the package.")
+                }),
+            ),
+        })
+        .collect()
+}
+
+fn render_package_page(
+    connection: &PooledConnection,
+    package: &(Package, Repository, User),
+    request: &HttpRequest,
+    user: Option<User>,
+    error: Option<&str>,
+) -> WarehouseResult<HttpResponse> {
+    let downloads = package.0.list_downloads(connection)?;
+    let versions: Vec<(Version, User, i64)> = package
+        .0
+        .list_versions(connection)?
+        .into_iter()
+        .map(|(version, user)| {
+            let count = downloads
+                .iter()
+                .filter(|download| download.version == version.version)
+                .map(|download| download.count)
+                .sum();
+            (version, user, count)
+        })
+        .collect();
+    view!(request, "route/package/detail", [
+        "user" => &user,
+        "package" => package,
+        "files" => &package.0.list_files(connection)?,
+        "dependencies" => &package.0.list_dependencies(connection)?,
+        "dependency_kinds" => &package::DEPENDENCY_KINDS,
+        "required_by" => &package.0.list_reverse_dependencies(connection, "depend")?,
+        "optional_for" => &package.0.list_reverse_dependencies(connection, "optdepend")?,
+        "versions" => &versions,
+        "downloads" => &downloads.iter().map(|download| download.count).sum::<i64>(),
+        "download_trend" => &download_trend(&downloads, Utc::now().naive_utc().date()),
+        "repositories" => &Repository::list(connection)?,
+        "error" => &error
+    ])
+}
+
+pub async fn serve_bootstrap_page(
+    auth: Authentication,
+    connection: PooledConnection,
+    request: HttpRequest,
+) -> WarehouseResult<HttpResponse> {
+    view!(&request, "route/package/bootstrap", [
+        "user" => &auth.user(),
+        "repositories" => &Repository::list(&connection)?,
+        "users" => &User::list(&connection)?
+    ])
+}
+
+pub async fn serve_file_search_page(
+    auth: Authentication,
+    config: Data<Config>,
+    connection: PooledConnection,
+    query: Query<FileSearchQuery>,
+    request: HttpRequest,
+) -> WarehouseResult<HttpResponse> {
+    if query.page < 1 {
+        return Err(WarehouseError::InvalidPathData);
+    }
+    let search = query.q.trim();
+    let offset = (i64::from(query.page) - 1) * i64::from(config.ui.paging_num);
+    let limit = i64::from(config.ui.paging_num);
+    let (files, count) = if search.is_empty() {
+        (Vec::new(), 0)
     } else {
-        Ok(HttpResponse::Found().header(LOCATION, "/").finish())
+        (
+            File::search(&connection, search, offset, limit)?,
+            File::count_search(&connection, search)?,
+        )
+    };
+    let accepts_json = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .into_iter()
+        .any(|accept| accept.contains("application/json"));
+    if accepts_json {
+        let results: Vec<FileSearchResult> = files
+            .iter()
+            .map(|(file, package, repository)| FileSearchResult {
+                path: &file.name,
+                name: &package.name,
+                version: &package.version,
+                architecture: &package.architecture,
+                repository: &repository.name,
+            })
+            .collect();
+        return Ok(HttpResponse::Ok()
+            .header("X-Total-Count", count.to_string())
+            .json(results));
     }
+    let page_count = (count as f64 / f64::from(config.ui.paging_num)).ceil() as i64;
+    view!(&request, "route/package/files", [
+        "user" => &auth.user(),
+        "page" => &query.page,
+        "page_count" => &page_count,
+        "count" => &count,
+        "files" => &files,
+        "q" => &search
+    ])
 }
 
 pub async fn serve_import_package_page(
@@ -152,13 +704,75 @@ pub async fn serve_import_package_page(
     ])
 }
 
-pub async fn serve_package_archive(path: Path<PackageFilePath>) -> Result<NamedFile> {
-    Ok(NamedFile::open(&path::package_file(
-        &path.repository,
-        &path.architecture,
-        &path.name,
-        &path.extension,
-    ))?)
+pub async fn serve_package_archive(
+    downloads: Data<DownloadCounter>,
+    path: Path<PackageFilePath>,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+) -> Result<HttpResponse> {
+    let response = storage
+        .serve(
+            &path::package_file(
+                &path.repository,
+                &path.architecture,
+                &path.name,
+                &path.extension,
+            ),
+            &request,
+        )
+        .await?;
+    if response.status().is_success() || response.status() == StatusCode::FOUND {
+        downloads.count(
+            &path.repository,
+            &path.architecture,
+            &path.name,
+            &path.extension,
+        );
+    }
+    Ok(response)
+}
+
+pub async fn serve_package_feed(
+    connection: PooledConnection,
+    path: Path<FeedPath>,
+    query: Query<FeedQuery>,
+    request: HttpRequest,
+) -> WarehouseResult<HttpResponse> {
+    let filter = PackageFilter {
+        repository: &query.repository,
+        architecture: &query.architecture,
+        maintainer: query.maintainer.trim(),
+        ..PackageFilter::default()
+    };
+    let versions = Version::list_recent(&connection, &filter, FEED_ENTRY_COUNT)?;
+    let mut title = String::from("Warehouse package updates");
+    if !filter.repository.is_empty() {
+        title.push_str(&format!(" in {}", filter.repository));
+    }
+    if !filter.architecture.is_empty() {
+        title.push_str(&format!(" for {}", filter.architecture));
+    }
+    if !filter.maintainer.is_empty() {
+        title.push_str(&format!(" by {}", filter.maintainer));
+    }
+    let connection_info = request.connection_info();
+    let base_url = format!("{}://{}", connection_info.scheme(), connection_info.host());
+    let feed = Feed {
+        title,
+        url: format!("{}{}", base_url, request.uri()),
+        link: format!("{}/package/list{}", base_url, feed_query(&filter)),
+        base_url: base_url.clone(),
+        versions: &versions,
+    };
+    if path.format == "atom" {
+        Ok(HttpResponse::Ok()
+            .content_type("application/atom+xml; charset=utf-8")
+            .body(feed.write_atom()?))
+    } else {
+        Ok(HttpResponse::Ok()
+            .content_type("application/rss+xml; charset=utf-8")
+            .body(feed.write_rss()?))
+    }
 }
 
 pub async fn serve_package_list_page(
@@ -168,27 +782,75 @@ pub async fn serve_package_list_page(
     query: Query<PackageListQuery>,
     request: HttpRequest,
 ) -> WarehouseResult<HttpResponse> {
-    if query.page > 0 {
+    if query.is_valid() {
         let offset = (i64::from(query.page) - 1) * i64::from(config.ui.paging_num);
         let limit = i64::from(config.ui.paging_num);
-        let packages = Package::list(&connection, offset, limit)?;
-        let page_count =
-            (Package::count(&connection)? as f64 / f64::from(config.ui.paging_num)).ceil() as i64;
+        let filter = query.filter();
+        let packages = Package::list(&connection, &filter, offset, limit)?;
+        let page_count = (Package::count(&connection, &filter)? as f64
+            / f64::from(config.ui.paging_num))
+        .ceil() as i64;
+        let repositories = Repository::list(&connection)?;
+        let mut architectures: BTreeSet<&str> = repositories
+            .iter()
+            .flat_map(|repository| repository.architectures.split_whitespace())
+            .collect();
+        architectures.insert("any");
+        let filters: BTreeMap<&str, &str> = [
+            ("q", filter.query),
+            ("repository", filter.repository),
+            ("architecture", filter.architecture),
+            ("maintainer", filter.maintainer),
+            ("sort", filter.sort),
+            ("order", &query.order),
+        ]
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .cloned()
+        .collect();
         view!(&request, "route/package/list", [
             "user" => &auth.user(),
             "page" => &query.page,
             "page_count" => &page_count,
-            "packages" => &packages
+            "packages" => &packages,
+            "repositories" => &repositories,
+            "architectures" => &architectures,
+            "sorts" => &PACKAGE_SORTS,
+            "filters" => &filters,
+            "feed_query" => &feed_query(&filter)
         ])
     } else {
         Err(WarehouseError::InvalidPathData)
     }
 }
 
-pub async fn serve_repository_database(path: Path<PackageFilePath>) -> Result<NamedFile> {
-    Ok(NamedFile::open(&path::repository_file(
-        &path.repository,
-        &path.architecture,
-        &path.extension,
-    ))?)
+pub async fn serve_package_signature(
+    path: Path<PackageFilePath>,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+) -> Result<HttpResponse> {
+    storage
+        .serve(
+            &path::package_signature_file(
+                &path.repository,
+                &path.architecture,
+                &path.name,
+                &path.extension,
+            ),
+            &request,
+        )
+        .await
+}
+
+pub async fn serve_repository_database(
+    path: Path<PackageFilePath>,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+) -> Result<HttpResponse> {
+    storage
+        .serve(
+            &path::repository_file(&path.repository, &path.architecture, &path.extension),
+            &request,
+        )
+        .await
 }
@@ -1,17 +1,23 @@
 use crate::{
     database::{
         schema::{
-            warehouse_package, warehouse_package_dependency, warehouse_package_file,
-            warehouse_package_version, warehouse_repository, warehouse_user,
+            warehouse_package, warehouse_package_dependency, warehouse_package_download,
+            warehouse_package_file, warehouse_package_version, warehouse_repository,
+            warehouse_user,
         },
-        Connection,
+        search, Backend, Connection, BIGINT_CAST,
     },
     service::{repository::model::Repository, user::model::User},
+    utils::package,
 };
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::{
-    dsl, result::Error, BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl,
-    RunQueryDsl,
+    dsl,
+    query_dsl::GroupByDsl,
+    result::Error,
+    sql_types::{BigInt, Date, Text},
+    BoolExpressionMethods, Connection as _, EscapeExpressionMethods, ExpressionMethods, JoinOnDsl,
+    NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, TextExpressionMethods,
 };
 use serde::{Deserialize, Serialize};
 
@@ -33,12 +39,26 @@ pub struct Package {
     pub extension: String,
     pub repository_id: String,
     pub maintainer_id: String,
+    pub sha256sum: String,
+    pub md5sum: String,
+}
+
+/// Narrows and orders a package listing; empty fields are ignored.
+#[derive(Default)]
+pub struct PackageFilter<'a> {
+    pub query: &'a str,
+    pub repository: &'a str,
+    pub architecture: &'a str,
+    pub maintainer: &'a str,
+    pub sort: &'a str,
+    pub descending: bool,
 }
 
 impl Package {
-    pub fn count(connection: &Connection) -> Result<i64, Error> {
+    pub fn count(connection: &Connection, filter: &PackageFilter) -> Result<i64, Error> {
         Ok(warehouse_package::table
             .select(dsl::count_star())
+            .filter(warehouse_package::id.eq_any(Package::filter_ids(filter)))
             .first(connection)?)
     }
 
@@ -64,6 +84,15 @@ impl Package {
         Ok(())
     }
 
+    pub fn delete_downloads(&self, connection: &Connection) -> Result<(), Error> {
+        dsl::delete(
+            warehouse_package_download::table
+                .filter(warehouse_package_download::package_id.eq(&self.id)),
+        )
+        .execute(connection)?;
+        Ok(())
+    }
+
     pub fn delete_files(&self, connection: &Connection) -> Result<(), Error> {
         dsl::delete(
             warehouse_package_file::table.filter(warehouse_package_file::package_id.eq(&self.id)),
@@ -81,6 +110,39 @@ impl Package {
         Ok(())
     }
 
+    fn filter_ids<'a>(
+        filter: &PackageFilter<'a>,
+    ) -> warehouse_package::BoxedQuery<'a, Backend, Text> {
+        let mut ids = warehouse_package::table
+            .select(warehouse_package::id)
+            .into_boxed();
+        if !filter.query.is_empty() {
+            ids = ids.filter(warehouse_package::id.eq_any(Package::search_ids(filter.query)));
+        }
+        if !filter.repository.is_empty() {
+            ids = ids.filter(
+                warehouse_package::repository_id.eq_any(
+                    warehouse_repository::table
+                        .select(warehouse_repository::id)
+                        .filter(warehouse_repository::name.eq(filter.repository)),
+                ),
+            );
+        }
+        if !filter.architecture.is_empty() {
+            ids = ids.filter(warehouse_package::architecture.eq(filter.architecture));
+        }
+        if !filter.maintainer.is_empty() {
+            ids = ids.filter(
+                warehouse_package::maintainer_id.eq_any(
+                    warehouse_user::table
+                        .select(warehouse_user::id)
+                        .filter(warehouse_user::name.eq(filter.maintainer)),
+                ),
+            );
+        }
+        ids
+    }
+
     pub fn find_by_name_repository_and_architecture(
         connection: &Connection,
         name: &str,
@@ -105,12 +167,15 @@ impl Package {
             .optional()?)
     }
 
+    /// Lists the packages matching a filter. Searches rank exact then partial name matches first
+    /// unless another sort is requested.
     pub fn list(
         connection: &Connection,
+        filter: &PackageFilter,
         offset: i64,
         limit: i64,
     ) -> Result<Vec<(Package, Repository, User)>, Error> {
-        Ok(warehouse_package::table
+        let query = warehouse_package::table
             .inner_join(warehouse_repository::table)
             .inner_join(warehouse_user::table)
             .select((
@@ -118,12 +183,181 @@ impl Package {
                 warehouse_repository::all_columns,
                 warehouse_user::all_columns,
             ))
-            .order_by(warehouse_package::name)
+            .filter(warehouse_package::id.eq_any(Package::filter_ids(filter)))
+            .into_boxed();
+        let query = match (filter.sort, filter.descending) {
+            ("build_date", false) => query.order_by(warehouse_package::build_date.asc()),
+            ("build_date", true) => query.order_by(warehouse_package::build_date.desc()),
+            ("modification_date", false) => {
+                query.order_by(warehouse_package::modification_date.asc())
+            }
+            ("modification_date", true) => {
+                query.order_by(warehouse_package::modification_date.desc())
+            }
+            ("size", false) => query.order_by(warehouse_package::installed_size.asc()),
+            ("size", true) => query.order_by(warehouse_package::installed_size.desc()),
+            ("name", true) => query.order_by(warehouse_package::name.desc()),
+            ("", _) if !filter.query.is_empty() => query.order_by((
+                warehouse_package::name.eq(filter.query).desc(),
+                search::lower(warehouse_package::name)
+                    .like(search::like_pattern(filter.query))
+                    .escape('\\')
+                    .desc(),
+            )),
+            _ => query,
+        };
+        Ok(query
+            .then_order_by(warehouse_package::name)
+            .then_order_by(warehouse_repository::name)
             .offset(offset)
             .limit(limit)
             .load(connection)?)
     }
 
+    pub fn list_architectures_by_repository(
+        connection: &Connection,
+        repository_id: &str,
+    ) -> Result<Vec<String>, Error> {
+        Ok(warehouse_package::table
+            .select(warehouse_package::architecture)
+            .filter(warehouse_package::repository_id.eq(repository_id))
+            .distinct()
+            .order_by(warehouse_package::architecture)
+            .load(connection)?)
+    }
+
+    pub fn list_by_dependency(
+        connection: &Connection,
+        kind: &str,
+        name: &str,
+        limit: i64,
+    ) -> Result<Vec<(Package, Repository, User)>, Error> {
+        Ok(warehouse_package::table
+            .inner_join(warehouse_repository::table)
+            .inner_join(warehouse_user::table)
+            .select((
+                warehouse_package::all_columns,
+                warehouse_repository::all_columns,
+                warehouse_user::all_columns,
+            ))
+            .filter(
+                warehouse_package::id.eq_any(
+                    warehouse_package_dependency::table
+                        .select(warehouse_package_dependency::package_id)
+                        .filter(
+                            warehouse_package_dependency::kind
+                                .eq(kind)
+                                .and(warehouse_package_dependency::name.eq(name)),
+                        ),
+                ),
+            )
+            .order_by((warehouse_package::name, warehouse_repository::name))
+            .limit(limit)
+            .load(connection)?)
+    }
+
+    pub fn list_by_maintainer(
+        connection: &Connection,
+        maintainer: &str,
+        limit: i64,
+    ) -> Result<Vec<(Package, Repository, User)>, Error> {
+        Ok(warehouse_package::table
+            .inner_join(warehouse_repository::table)
+            .inner_join(warehouse_user::table)
+            .select((
+                warehouse_package::all_columns,
+                warehouse_repository::all_columns,
+                warehouse_user::all_columns,
+            ))
+            .filter(warehouse_user::name.eq(maintainer))
+            .order_by((warehouse_package::name, warehouse_repository::name))
+            .limit(limit)
+            .load(connection)?)
+    }
+
+    /// Lists the packages whose name, or description when asked, contains the query.
+    pub fn list_by_name_search(
+        connection: &Connection,
+        query: &str,
+        description: bool,
+        limit: i64,
+    ) -> Result<Vec<(Package, Repository, User)>, Error> {
+        let pattern = search::like_pattern(query);
+        let query = warehouse_package::table
+            .inner_join(warehouse_repository::table)
+            .inner_join(warehouse_user::table)
+            .select((
+                warehouse_package::all_columns,
+                warehouse_repository::all_columns,
+                warehouse_user::all_columns,
+            ))
+            .into_boxed();
+        let query = if description {
+            query.filter(
+                search::lower(warehouse_package::name)
+                    .like(pattern.clone())
+                    .escape('\\')
+                    .or(search::lower(warehouse_package::description)
+                        .like(pattern)
+                        .escape('\\')),
+            )
+        } else {
+            query.filter(
+                search::lower(warehouse_package::name)
+                    .like(pattern)
+                    .escape('\\'),
+            )
+        };
+        Ok(query
+            .order_by((warehouse_package::name, warehouse_repository::name))
+            .limit(limit)
+            .load(connection)?)
+    }
+
+    pub fn list_by_names(
+        connection: &Connection,
+        names: &[String],
+    ) -> Result<Vec<(Package, Repository, User)>, Error> {
+        Ok(warehouse_package::table
+            .inner_join(warehouse_repository::table)
+            .inner_join(warehouse_user::table)
+            .select((
+                warehouse_package::all_columns,
+                warehouse_repository::all_columns,
+                warehouse_user::all_columns,
+            ))
+            .filter(warehouse_package::name.eq_any(names))
+            .order_by((warehouse_package::name, warehouse_repository::name))
+            .load(connection)?)
+    }
+
+    pub fn list_by_repository(
+        connection: &Connection,
+        repository_id: &str,
+    ) -> Result<Vec<Package>, Error> {
+        Ok(warehouse_package::table
+            .filter(warehouse_package::repository_id.eq(repository_id))
+            .order_by(warehouse_package::name)
+            .load(connection)?)
+    }
+
+    pub fn list_by_repository_and_architecture(
+        connection: &Connection,
+        repository_id: &str,
+        architecture: &str,
+    ) -> Result<Vec<Package>, Error> {
+        Ok(warehouse_package::table
+            .filter(
+                warehouse_package::repository_id.eq(repository_id).and(
+                    warehouse_package::architecture
+                        .eq(architecture)
+                        .or(warehouse_package::architecture.eq("any")),
+                ),
+            )
+            .order_by(warehouse_package::name)
+            .load(connection)?)
+    }
+
     pub fn list_dependencies(&self, connection: &Connection) -> Result<Vec<Dependency>, Error> {
         Ok(warehouse_package_dependency::table
             .select(warehouse_package_dependency::all_columns)
@@ -132,6 +366,13 @@ impl Package {
             .load(connection)?)
     }
 
+    pub fn list_downloads(&self, connection: &Connection) -> Result<Vec<Download>, Error> {
+        Ok(warehouse_package_download::table
+            .filter(warehouse_package_download::package_id.eq(&self.id))
+            .order_by(warehouse_package_download::day)
+            .load(connection)?)
+    }
+
     pub fn list_files(&self, connection: &Connection) -> Result<Vec<File>, Error> {
         Ok(warehouse_package_file::table
             .select(warehouse_package_file::all_columns)
@@ -140,6 +381,111 @@ impl Package {
             .load(connection)?)
     }
 
+    /// Lists the packages with the most downloads, along with their total and their downloads
+    /// since a given day.
+    pub fn list_most_downloaded(
+        connection: &Connection,
+        since: NaiveDate,
+        limit: usize,
+    ) -> Result<Vec<(Package, Repository, i64, i64)>, Error> {
+        let total = || {
+            dsl::sql::<BigInt>(&format!(
+                "CAST(SUM(warehouse_package_download.count) AS {})",
+                BIGINT_CAST
+            ))
+        };
+        let counts: Vec<(String, i64, i64)> = warehouse_package_download::table
+            .select((
+                warehouse_package_download::package_id,
+                total(),
+                dsl::sql::<BigInt>("CAST(SUM(CASE WHEN warehouse_package_download.day >= ")
+                    .bind::<Date, _>(since)
+                    .sql(&format!(
+                        " THEN warehouse_package_download.count ELSE 0 END) AS {})",
+                        BIGINT_CAST
+                    )),
+            ))
+            .group_by(warehouse_package_download::package_id)
+            .order_by((total().desc(), warehouse_package_download::package_id))
+            .limit(limit as i64)
+            .load(connection)?;
+        let packages: Vec<(Package, Repository)> = warehouse_package::table
+            .inner_join(warehouse_repository::table)
+            .select((
+                warehouse_package::all_columns,
+                warehouse_repository::all_columns,
+            ))
+            .filter(warehouse_package::id.eq_any(counts.iter().map(|(id, _, _)| id)))
+            .load(connection)?;
+        Ok(counts
+            .into_iter()
+            .filter_map(|(id, total, recent)| {
+                packages.iter().find(|(package, _)| package.id == id).map(
+                    |(package, repository)| (package.clone(), repository.clone(), total, recent),
+                )
+            })
+            .collect())
+    }
+
+    /// Lists the packages with a `kind` relation to this package or to anything it provides, in
+    /// its repository and the repositories that depend on it.
+    pub fn list_reverse_dependencies(
+        &self,
+        connection: &Connection,
+        kind: &str,
+    ) -> Result<Vec<(Package, Repository)>, Error> {
+        let repository: Repository = warehouse_repository::table
+            .find(&self.repository_id)
+            .first(connection)?;
+        let repository_ids: Vec<String> = Repository::list(connection)?
+            .into_iter()
+            .filter(|dependent| {
+                dependent.id == repository.id
+                    || dependent
+                        .depends_on
+                        .split_whitespace()
+                        .any(|name| name == repository.name)
+            })
+            .map(|dependent| dependent.id)
+            .collect();
+        let provides: Vec<String> = warehouse_package_dependency::table
+            .select(warehouse_package_dependency::name)
+            .filter(
+                warehouse_package_dependency::package_id
+                    .eq(&self.id)
+                    .and(warehouse_package_dependency::kind.eq("provides")),
+            )
+            .load(connection)?;
+        Ok(warehouse_package::table
+            .inner_join(warehouse_package_dependency::table)
+            .inner_join(warehouse_repository::table)
+            .select((
+                warehouse_package::all_columns,
+                warehouse_repository::all_columns,
+            ))
+            .filter(
+                warehouse_package_dependency::kind
+                    .eq(kind)
+                    .and(
+                        warehouse_package_dependency::name
+                            .eq(&self.name)
+                            .or(warehouse_package_dependency::name.eq_any(provides)),
+                    )
+                    .and(warehouse_package::id.ne(&self.id))
+                    .and(warehouse_package::repository_id.eq_any(repository_ids)),
+            )
+            .distinct()
+            .order_by(warehouse_package::name)
+            .load::<(Package, Repository)>(connection)?
+            .into_iter()
+            .filter(|(package, _)| {
+                self.architecture == "any"
+                    || package.architecture == "any"
+                    || package.architecture == self.architecture
+            })
+            .collect())
+    }
+
     pub fn list_versions(&self, connection: &Connection) -> Result<Vec<(Version, User)>, Error> {
         Ok(warehouse_package_version::table
             .inner_join(warehouse_user::table)
@@ -152,6 +498,48 @@ impl Package {
             .load(connection)?)
     }
 
+    pub fn list_with_provides_by_repository(
+        connection: &Connection,
+        repository_id: &str,
+    ) -> Result<Vec<(Package, Option<Dependency>)>, Error> {
+        Ok(warehouse_package::table
+            .left_join(
+                warehouse_package_dependency::table.on(warehouse_package_dependency::package_id
+                    .eq(warehouse_package::id)
+                    .and(warehouse_package_dependency::kind.eq("provides"))),
+            )
+            .select((
+                warehouse_package::all_columns,
+                warehouse_package_dependency::all_columns.nullable(),
+            ))
+            .filter(warehouse_package::repository_id.eq(repository_id))
+            .load(connection)?)
+    }
+
+    fn search_ids(query: &str) -> warehouse_package::BoxedQuery<'static, Backend, Text> {
+        let pattern = search::like_pattern(query);
+        warehouse_package::table
+            .select(warehouse_package::id)
+            .filter(
+                search::lower(warehouse_package::name)
+                    .like(pattern.clone())
+                    .escape('\\')
+                    .or(search::full_text_match(query))
+                    .or(warehouse_package::id.eq_any(
+                        warehouse_package_dependency::table
+                            .select(warehouse_package_dependency::package_id)
+                            .filter(
+                                warehouse_package_dependency::kind.eq("provides").and(
+                                    search::lower(warehouse_package_dependency::name)
+                                        .like(pattern)
+                                        .escape('\\'),
+                                ),
+                            ),
+                    )),
+            )
+            .into_boxed()
+    }
+
     pub fn update(&self, connection: &Connection) -> Result<(), Error> {
         dsl::update(warehouse_package::table)
             .set(self)
@@ -167,6 +555,10 @@ pub struct Dependency {
     pub id: String,
     pub name: String,
     pub package_id: String,
+    pub kind: String,
+    pub operator: String,
+    pub version: String,
+    pub description: String,
 }
 
 impl Dependency {
@@ -176,6 +568,80 @@ impl Dependency {
             .execute(connection)?;
         Ok(())
     }
+
+    /// Splits the constraint of dependencies imported before it was stored apart from the name.
+    pub fn split_constraints(connection: &Connection) -> Result<(), Error> {
+        connection.transaction(|| {
+            let dependencies: Vec<(String, String, String)> = warehouse_package_dependency::table
+                .select((
+                    warehouse_package_dependency::id,
+                    warehouse_package_dependency::kind,
+                    warehouse_package_dependency::name,
+                ))
+                .filter(
+                    warehouse_package_dependency::name
+                        .like("%<%")
+                        .or(warehouse_package_dependency::name.like("%=%"))
+                        .or(warehouse_package_dependency::name.like("%>%")),
+                )
+                .load(connection)?;
+            for (id, kind, name) in dependencies {
+                let info = package::DependencyInfo::parse(&kind, &name);
+                dsl::update(warehouse_package_dependency::table.find(id))
+                    .set((
+                        warehouse_package_dependency::name.eq(info.name),
+                        warehouse_package_dependency::operator.eq(info.operator),
+                        warehouse_package_dependency::version.eq(info.version),
+                    ))
+                    .execute(connection)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Counts the downloads of a package version for an architecture on a given day.
+#[derive(Clone, Deserialize, Insertable, Queryable, Serialize)]
+#[table_name = "warehouse_package_download"]
+pub struct Download {
+    pub id: String,
+    pub day: NaiveDate,
+    pub version: String,
+    pub architecture: String,
+    pub count: i64,
+    pub package_id: String,
+}
+
+impl Download {
+    pub fn create(&self, connection: &Connection) -> Result<(), Error> {
+        dsl::insert_into(warehouse_package_download::table)
+            .values(self)
+            .execute(connection)?;
+        Ok(())
+    }
+
+    /// Adds to an existing counter, returning whether there was one.
+    pub fn increment(
+        connection: &Connection,
+        package_id: &str,
+        version: &str,
+        architecture: &str,
+        day: NaiveDate,
+        count: i64,
+    ) -> Result<bool, Error> {
+        Ok(dsl::update(
+            warehouse_package_download::table.filter(
+                warehouse_package_download::package_id
+                    .eq(package_id)
+                    .and(warehouse_package_download::version.eq(version))
+                    .and(warehouse_package_download::architecture.eq(architecture))
+                    .and(warehouse_package_download::day.eq(day)),
+            ),
+        )
+        .set(warehouse_package_download::count.eq(warehouse_package_download::count + count))
+        .execute(connection)?
+            > 0)
+    }
 }
 
 #[derive(Clone, Deserialize, Insertable, Queryable, Serialize)]
@@ -185,18 +651,97 @@ pub struct File {
     pub name: String,
     pub size: i64,
     pub package_id: String,
+    pub basename: String,
 }
 
 impl File {
+    pub fn count_search(connection: &Connection, query: &str) -> Result<i64, Error> {
+        Ok(warehouse_package_file::table
+            .select(dsl::count_star())
+            .filter(warehouse_package_file::id.eq_any(File::search_ids(query)))
+            .first(connection)?)
+    }
+
     pub fn create(&self, connection: &Connection) -> Result<(), Error> {
         dsl::insert_into(warehouse_package_file::table)
             .values(self)
             .execute(connection)?;
         Ok(())
     }
+
+    /// Fills the basename of files imported before it was stored.
+    pub fn fill_basenames(connection: &Connection) -> Result<(), Error> {
+        connection.transaction(|| {
+            let files: Vec<(String, String)> = warehouse_package_file::table
+                .select((warehouse_package_file::id, warehouse_package_file::name))
+                .filter(warehouse_package_file::basename.eq(""))
+                .load(connection)?;
+            for (id, name) in files {
+                dsl::update(warehouse_package_file::table.find(id))
+                    .set(warehouse_package_file::basename.eq(package::file_basename(&name)))
+                    .execute(connection)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Looks up files by exact path, basename or glob (`*` and `?`), like `pacman -F`.
+    pub fn search(
+        connection: &Connection,
+        query: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(File, Package, Repository)>, Error> {
+        Ok(warehouse_package_file::table
+            .inner_join(warehouse_package::table.inner_join(warehouse_repository::table))
+            .select((
+                warehouse_package_file::all_columns,
+                warehouse_package::all_columns,
+                warehouse_repository::all_columns,
+            ))
+            .filter(warehouse_package_file::id.eq_any(File::search_ids(query)))
+            .order_by((
+                warehouse_package_file::name,
+                warehouse_package::name,
+                warehouse_repository::name,
+                warehouse_package::architecture,
+            ))
+            .offset(offset)
+            .limit(limit)
+            .load(connection)?)
+    }
+
+    fn search_ids(query: &str) -> warehouse_package_file::BoxedQuery<'static, Backend, Text> {
+        let path = query.trim().trim_start_matches('/');
+        let basename = package::file_basename(path);
+        let mut ids = warehouse_package_file::table
+            .select(warehouse_package_file::id)
+            .into_boxed();
+        ids = if search::is_glob(&basename) {
+            ids.filter(
+                warehouse_package_file::basename
+                    .like(search::glob_pattern(&basename))
+                    .escape('\\'),
+            )
+        } else {
+            ids.filter(warehouse_package_file::basename.eq(basename))
+        };
+        if path.contains('/') {
+            ids = if search::is_glob(path) {
+                ids.filter(
+                    warehouse_package_file::name
+                        .like(search::glob_pattern(path))
+                        .escape('\\'),
+                )
+            } else {
+                ids.filter(warehouse_package_file::name.eq(path.to_string()))
+            };
+        }
+        ids
+    }
 }
 
-#[derive(Clone, Deserialize, Insertable, Queryable, Serialize)]
+#[derive(AsChangeset, Clone, Deserialize, Insertable, Queryable, Serialize)]
 #[table_name = "warehouse_package_version"]
 pub struct Version {
     pub id: String,
@@ -204,6 +749,9 @@ pub struct Version {
     pub version: String,
     pub maintainer_id: String,
     pub package_id: String,
+    pub extension: String,
+    pub sha256sum: String,
+    pub md5sum: String,
 }
 
 impl Version {
@@ -213,4 +761,216 @@ impl Version {
             .execute(connection)?;
         Ok(())
     }
+
+    pub fn find_by_id_and_package(
+        connection: &Connection,
+        id: &str,
+        package_id: &str,
+    ) -> Result<Option<Version>, Error> {
+        Ok(warehouse_package_version::table
+            .filter(
+                warehouse_package_version::id
+                    .eq(id)
+                    .and(warehouse_package_version::package_id.eq(package_id)),
+            )
+            .first(connection)
+            .optional()?)
+    }
+
+    /// Lists the versions of the packages matching a filter, newest first, with their package,
+    /// repository and uploader.
+    pub fn list_recent(
+        connection: &Connection,
+        filter: &PackageFilter,
+        limit: i64,
+    ) -> Result<Vec<(Version, Package, Repository, User)>, Error> {
+        Ok(warehouse_package_version::table
+            .inner_join(warehouse_package::table.inner_join(warehouse_repository::table))
+            .inner_join(warehouse_user::table)
+            .select((
+                warehouse_package_version::all_columns,
+                warehouse_package::all_columns,
+                warehouse_repository::all_columns,
+                warehouse_user::all_columns,
+            ))
+            .filter(warehouse_package_version::package_id.eq_any(Package::filter_ids(filter)))
+            .order_by(warehouse_package_version::creation_date.desc())
+            .limit(limit)
+            .load(connection)?)
+    }
+
+    pub fn update(&self, connection: &Connection) -> Result<(), Error> {
+        dsl::update(warehouse_package_version::table)
+            .set(self)
+            .filter(warehouse_package_version::id.eq(&self.id))
+            .execute(connection)?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+pub mod tests {
+    use super::*;
+    use crate::{
+        core::error::WarehouseResult,
+        database::{self, PooledConnection},
+    };
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    pub fn create_dependency(
+        connection: &Connection,
+        package: &Package,
+        kind: &str,
+        name: &str,
+    ) -> Result<(), Error> {
+        Dependency {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            package_id: package.id.clone(),
+            kind: kind.to_string(),
+            operator: String::new(),
+            version: String::new(),
+            description: String::new(),
+        }
+        .create(connection)
+    }
+
+    pub fn create_package(
+        connection: &Connection,
+        repository: &Repository,
+        user: &User,
+        name: &str,
+        version: &str,
+    ) -> Result<Package, Error> {
+        let now = Utc::now().naive_utc();
+        let package = Package {
+            id: Uuid::new_v4().to_string(),
+            creation_date: now,
+            modification_date: now,
+            name: name.to_string(),
+            version: version.to_string(),
+            description: String::new(),
+            url: String::new(),
+            build_date: now,
+            compressed_size: 0,
+            installed_size: 0,
+            architecture: String::from("x86_64"),
+            license: String::new(),
+            extension: String::from("pkg.tar.zst"),
+            repository_id: repository.id.clone(),
+            maintainer_id: user.id.clone(),
+            sha256sum: String::new(),
+            md5sum: String::new(),
+        };
+        package.create(connection)?;
+        Ok(package)
+    }
+
+    pub fn create_repository(
+        connection: &Connection,
+        name: &str,
+        depends_on: &str,
+    ) -> Result<Repository, Error> {
+        let repository = Repository {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            extension: String::from("db.tar.zst"),
+            require_signature: false,
+            description: String::new(),
+            architectures: String::from("x86_64"),
+            retention: 3,
+            depends_on: depends_on.to_string(),
+            strict_dependencies: false,
+            snapshot_interval: 0,
+            snapshot_retention: 0,
+        };
+        repository.create(connection)?;
+        Ok(repository)
+    }
+
+    pub fn create_user(connection: &Connection) -> Result<User, Error> {
+        let user = User {
+            id: Uuid::new_v4().to_string(),
+            creation_date: Utc::now().naive_utc(),
+            name: String::from("admin"),
+            email: String::from("admin@example.org"),
+            password: String::new(),
+            admin: true,
+        };
+        user.create(connection)?;
+        Ok(user)
+    }
+
+    #[test]
+    fn list_reverse_dependencies_covers_downstream_repositories() -> WarehouseResult {
+        let (_directory, pool) = database::open_test_pool()?;
+        let connection = PooledConnection::get(&pool)?;
+        let user = create_user(&connection)?;
+        let core = create_repository(&connection, "core", "")?;
+        let extra = create_repository(&connection, "extra", "core")?;
+        let unrelated = create_repository(&connection, "unrelated", "")?;
+        let library = create_package(&connection, &core, &user, "library", "1.0-1")?;
+        let tool = create_package(&connection, &core, &user, "tool", "1.0-1")?;
+        create_dependency(&connection, &tool, "depend", "library")?;
+        create_dependency(&connection, &tool, "depend", "application")?;
+        let application = create_package(&connection, &extra, &user, "application", "1.0-1")?;
+        create_dependency(&connection, &application, "depend", "library")?;
+        create_dependency(&connection, &application, "optdepend", "library")?;
+        let other = create_package(&connection, &unrelated, &user, "other", "1.0-1")?;
+        create_dependency(&connection, &other, "depend", "library")?;
+        let names = |packages: Vec<(Package, Repository)>| -> Vec<(String, String)> {
+            packages
+                .into_iter()
+                .map(|(package, repository)| (repository.name, package.name))
+                .collect()
+        };
+        assert_eq!(
+            names(library.list_reverse_dependencies(&connection, "depend")?),
+            vec![
+                (String::from("extra"), String::from("application")),
+                (String::from("core"), String::from("tool")),
+            ]
+        );
+        assert_eq!(
+            names(library.list_reverse_dependencies(&connection, "optdepend")?),
+            vec![(String::from("extra"), String::from("application"))]
+        );
+        // Upstream repositories cannot depend on a downstream package.
+        assert!(application
+            .list_reverse_dependencies(&connection, "depend")?
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn split_constraints_backfills_operator_and_version() -> WarehouseResult {
+        let (_directory, pool) = database::open_test_pool()?;
+        let connection = PooledConnection::get(&pool)?;
+        let user = create_user(&connection)?;
+        let repository = create_repository(&connection, "core", "")?;
+        let package = create_package(&connection, &repository, &user, "bash", "5.1-1")?;
+        for name in &["glibc>=2.33", "ncurses", "readline=8.1"] {
+            create_dependency(&connection, &package, "depend", name)?;
+        }
+        Dependency::split_constraints(&connection)?;
+        let mut dependencies: Vec<(String, String, String)> = package
+            .list_dependencies(&connection)?
+            .into_iter()
+            .map(|dependency| (dependency.name, dependency.operator, dependency.version))
+            .collect();
+        dependencies.sort();
+        let expected = |name: &str, operator: &str, version: &str| {
+            (name.to_string(), operator.to_string(), version.to_string())
+        };
+        assert_eq!(
+            dependencies,
+            vec![
+                expected("glibc", ">=", "2.33"),
+                expected("ncurses", "", ""),
+                expected("readline", "=", "8.1"),
+            ]
+        );
+        Ok(())
+    }
 }
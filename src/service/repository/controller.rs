@@ -0,0 +1,282 @@
+use crate::{
+    core::{
+        config::Config,
+        error::{WarehouseError, WarehouseResult},
+    },
+    database::PooledConnection,
+    service::{package::model::Package, repository::model::Repository, user::model::User},
+    utils::{path, regex::Regexes, repository, storage::Storage},
+    view,
+};
+use actix_web::{
+    http::header::LOCATION,
+    web::{self, Data, Form, Path},
+    HttpRequest, HttpResponse,
+};
+use diesel::Connection;
+use serde::Deserialize;
+use std::{collections::BTreeSet, sync::Arc};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct RepositoryForm {
+    name: String,
+    description: String,
+    extension: String,
+    architectures: String,
+    #[serde(default)]
+    require_signature: bool,
+    retention: i32,
+    depends_on: String,
+    #[serde(default)]
+    strict_dependencies: bool,
+    snapshot_interval: i32,
+    snapshot_retention: i32,
+}
+
+impl RepositoryForm {
+    pub fn is_valid(&self, regexes: &Regexes) -> bool {
+        regexes.repository.is_match(&self.name)
+            && self.description.chars().count() <= 255
+            && repository::EXTENSIONS.contains(&self.extension.as_str())
+            && regexes.architectures.is_match(&self.architectures)
+            && !self
+                .architectures
+                .split_whitespace()
+                .any(|architecture| architecture == "any")
+            && (1..=100).contains(&self.retention)
+            && regexes.repositories.is_match(&self.depends_on)
+            && !self
+                .depends_on
+                .split_whitespace()
+                .any(|dependency| dependency == self.name)
+            && (0..=365).contains(&self.snapshot_interval)
+            && (0..=1000).contains(&self.snapshot_retention)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RepositoryPath {
+    name: String,
+}
+
+pub async fn delete_repository(
+    connection: PooledConnection,
+    path: Path<RepositoryPath>,
+    storage: Data<Arc<dyn Storage>>,
+) -> WarehouseResult<HttpResponse> {
+    match Repository::find_by_name(&connection, &path.name)? {
+        Some(repository) => {
+            web::block(move || {
+                connection.transaction::<_, WarehouseError, _>(|| {
+                    for package in Package::list_by_repository(&connection, &repository.id)? {
+                        package.delete_versions(&connection)?;
+                        package.delete_dependencies(&connection)?;
+                        package.delete_downloads(&connection)?;
+                        package.delete_files(&connection)?;
+                        package.delete(&connection)?;
+                    }
+                    repository.delete(&connection)?;
+                    rename_dependency(&connection, &repository.name, None)?;
+                    storage.delete_all(&path::repository_dir(&repository.name))?;
+                    Ok(())
+                })
+            })
+            .await?;
+            Ok(HttpResponse::Ok().into())
+        }
+        None => Ok(HttpResponse::NotFound().into()),
+    }
+}
+
+pub async fn handle_create_repository_post(
+    connection: PooledConnection,
+    form: Form<RepositoryForm>,
+    regexes: Data<Regexes>,
+    request: HttpRequest,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    if form.is_valid(&regexes) {
+        if Repository::find_by_name(&connection, &form.name)?.is_some() {
+            view!(&request, "route/repository/edit", [
+                "user" => &user,
+                "extensions" => &repository::EXTENSIONS,
+                "error" => "Repository already exists with that name."
+            ])
+        } else {
+            let repository = Repository {
+                id: Uuid::new_v4().to_string(),
+                name: form.name.clone(),
+                extension: form.extension.clone(),
+                require_signature: form.require_signature,
+                description: form.description.clone(),
+                architectures: form.architectures.clone(),
+                retention: form.retention,
+                depends_on: form.depends_on.clone(),
+                strict_dependencies: form.strict_dependencies,
+                snapshot_interval: form.snapshot_interval,
+                snapshot_retention: form.snapshot_retention,
+            };
+            repository.create(&connection)?;
+            Ok(HttpResponse::Found().header(LOCATION, "/admin").finish())
+        }
+    } else {
+        Err(WarehouseError::InvalidFormData)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_update_repository_post(
+    config: Data<Config>,
+    connection: PooledConnection,
+    form: Form<RepositoryForm>,
+    path: Path<RepositoryPath>,
+    regexes: Data<Regexes>,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    let mut repository = match Repository::find_by_name(&connection, &path.name)? {
+        Some(repository) => repository,
+        None => return Ok(HttpResponse::NotFound().into()),
+    };
+    if !form.is_valid(&regexes) {
+        return Err(WarehouseError::InvalidFormData);
+    }
+    if form.name != repository.name && Repository::find_by_name(&connection, &form.name)?.is_some()
+    {
+        return view!(&request, "route/repository/edit", [
+            "user" => &user,
+            "repository" => &repository,
+            "extensions" => &repository::EXTENSIONS,
+            "error" => "Repository already exists with that name."
+        ]);
+    }
+    web::block(move || {
+        connection.transaction::<_, WarehouseError, _>(|| {
+            let old_repository = repository.clone();
+            repository.name = form.name.clone();
+            repository.description = form.description.clone();
+            repository.extension = form.extension.clone();
+            repository.require_signature = form.require_signature;
+            repository.architectures = form.architectures.clone();
+            repository.retention = form.retention;
+            repository.depends_on = form.depends_on.clone();
+            repository.strict_dependencies = form.strict_dependencies;
+            repository.snapshot_interval = form.snapshot_interval;
+            repository.snapshot_retention = form.snapshot_retention;
+            repository.update(&connection)?;
+            if repository.name != old_repository.name {
+                rename_dependency(&connection, &old_repository.name, Some(&repository.name))?;
+                storage.rename_all(
+                    &path::repository_dir(&old_repository.name),
+                    &path::repository_dir(&repository.name),
+                )?;
+            }
+            if repository.extension != old_repository.extension
+                || repository.architectures != old_repository.architectures
+            {
+                let linked_repository = Repository {
+                    architectures: old_repository.architectures.clone(),
+                    ..repository.clone()
+                };
+                for package in Package::list_by_repository(&connection, &repository.id)?
+                    .iter()
+                    .filter(|package| package.architecture == "any")
+                {
+                    repository::unlink_package(
+                        &storage,
+                        &linked_repository,
+                        &package.name,
+                        &package.extension,
+                    )?;
+                    repository::link_package(
+                        &storage,
+                        &repository,
+                        &package.name,
+                        &package.extension,
+                    )?;
+                }
+                let mut architectures: BTreeSet<String> =
+                    Package::list_architectures_by_repository(&connection, &repository.id)?
+                        .into_iter()
+                        .collect();
+                architectures.extend(
+                    repository
+                        .architectures
+                        .split_whitespace()
+                        .chain(old_repository.architectures.split_whitespace())
+                        .map(String::from),
+                );
+                architectures.remove("any");
+                for architecture in architectures {
+                    repository::update_repository(
+                        &connection,
+                        &config,
+                        &storage,
+                        &repository,
+                        &architecture,
+                    )?;
+                }
+            }
+            Ok(())
+        })
+    })
+    .await?;
+    Ok(HttpResponse::Found().header(LOCATION, "/admin").finish())
+}
+
+fn rename_dependency(
+    connection: &PooledConnection,
+    old_name: &str,
+    new_name: Option<&str>,
+) -> Result<(), diesel::result::Error> {
+    for mut repository in Repository::list(connection)? {
+        if repository
+            .depends_on
+            .split_whitespace()
+            .any(|dependency| dependency == old_name)
+        {
+            repository.depends_on = repository
+                .depends_on
+                .split_whitespace()
+                .filter_map(|dependency| {
+                    if dependency == old_name {
+                        new_name
+                    } else {
+                        Some(dependency)
+                    }
+                })
+                .collect::<Vec<&str>>()
+                .join(" ");
+            repository.update(connection)?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn serve_create_repository_page(
+    request: HttpRequest,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    view!(&request, "route/repository/edit", [
+        "user" => &user,
+        "extensions" => &repository::EXTENSIONS
+    ])
+}
+
+pub async fn serve_update_repository_page(
+    connection: PooledConnection,
+    path: Path<RepositoryPath>,
+    request: HttpRequest,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    match Repository::find_by_name(&connection, &path.name)? {
+        Some(repository) => view!(&request, "route/repository/edit", [
+            "user" => &user,
+            "repository" => &repository,
+            "extensions" => &repository::EXTENSIONS
+        ]),
+        None => Ok(HttpResponse::NotFound().into()),
+    }
+}
@@ -1,16 +1,37 @@
 use crate::database::{schema::warehouse_repository, Connection};
-use diesel::{result::Error, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use diesel::{dsl, result::Error, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Deserialize, Insertable, Queryable, Serialize)]
+#[derive(AsChangeset, Clone, Deserialize, Insertable, Queryable, Serialize)]
 #[table_name = "warehouse_repository"]
 pub struct Repository {
     pub id: String,
     pub name: String,
     pub extension: String,
+    pub require_signature: bool,
+    pub description: String,
+    pub architectures: String,
+    pub retention: i32,
+    pub depends_on: String,
+    pub strict_dependencies: bool,
+    pub snapshot_interval: i32,
+    pub snapshot_retention: i32,
 }
 
 impl Repository {
+    pub fn create(&self, connection: &Connection) -> Result<(), Error> {
+        dsl::insert_into(warehouse_repository::table)
+            .values(self)
+            .execute(connection)?;
+        Ok(())
+    }
+
+    pub fn delete(&self, connection: &Connection) -> Result<(), Error> {
+        dsl::delete(warehouse_repository::table.filter(warehouse_repository::id.eq(&self.id)))
+            .execute(connection)?;
+        Ok(())
+    }
+
     pub fn find_by_name(connection: &Connection, name: &str) -> Result<Option<Repository>, Error> {
         Ok(warehouse_repository::table
             .filter(warehouse_repository::name.eq(name))
@@ -21,6 +42,15 @@ impl Repository {
     pub fn list(connection: &Connection) -> Result<Vec<Repository>, Error> {
         Ok(warehouse_repository::table
             .select(warehouse_repository::all_columns)
+            .order_by(warehouse_repository::name)
             .load(connection)?)
     }
+
+    pub fn update(&self, connection: &Connection) -> Result<(), Error> {
+        dsl::update(warehouse_repository::table)
+            .set(self)
+            .filter(warehouse_repository::id.eq(&self.id))
+            .execute(connection)?;
+        Ok(())
+    }
 }
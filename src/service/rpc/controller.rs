@@ -0,0 +1,270 @@
+use crate::{
+    core::error::WarehouseResult,
+    database::PooledConnection,
+    service::{
+        package::model::{Dependency, Package},
+        repository::model::Repository,
+        user::model::User,
+    },
+};
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+use std::{cmp::Ordering, collections::HashMap};
+use url::form_urlencoded;
+
+/// Searches matching more packages are refused, as the AUR does.
+const MAX_RESULTS: usize = 5000;
+const RPC_VERSION: u8 = 5;
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InfoResult {
+    #[serde(flatten)]
+    package: SearchResult,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    depends: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    make_depends: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    opt_depends: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    check_depends: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    conflicts: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    provides: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    replaces: Vec<String>,
+    license: Vec<String>,
+    keywords: Vec<String>,
+}
+
+impl InfoResult {
+    fn new(package: &(Package, Repository, User), dependencies: &[Dependency]) -> InfoResult {
+        let relations = |kind: &str| -> Vec<String> {
+            dependencies
+                .iter()
+                .filter(|dependency| dependency.kind == kind)
+                .map(|dependency| {
+                    let relation = format!(
+                        "{}{}{}",
+                        dependency.name, dependency.operator, dependency.version
+                    );
+                    if dependency.description.is_empty() {
+                        relation
+                    } else {
+                        format!("{}: {}", relation, dependency.description)
+                    }
+                })
+                .collect()
+        };
+        InfoResult {
+            package: SearchResult::from(package),
+            depends: relations("depend"),
+            make_depends: relations("makedepend"),
+            opt_depends: relations("optdepend"),
+            check_depends: relations("checkdepend"),
+            conflicts: relations("conflict"),
+            provides: relations("provides"),
+            replaces: relations("replaces"),
+            license: package
+                .0
+                .license
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+            keywords: Vec::new(),
+        }
+    }
+}
+
+/// Parameters of an RPC request. `arg[]` may be repeated for info requests.
+#[derive(Default)]
+pub struct RpcQuery {
+    version: Option<String>,
+    kind: Option<String>,
+    by: Option<String>,
+    args: Vec<String>,
+}
+
+impl RpcQuery {
+    fn parse(query: &str) -> RpcQuery {
+        let mut rpc_query = RpcQuery::default();
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "v" => rpc_query.version = Some(value.into_owned()),
+                "type" => rpc_query.kind = Some(value.into_owned()),
+                "by" => rpc_query.by = Some(value.into_owned()),
+                "arg" | "arg[]" => rpc_query.args.push(value.into_owned()),
+                _ => {}
+            }
+        }
+        rpc_query
+    }
+}
+
+#[derive(Serialize)]
+pub struct RpcResponse<T> {
+    version: u8,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    resultcount: usize,
+    results: Vec<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'static str>,
+}
+
+/// Warehouse has no numeric ids, votes or out-of-date flags, so these keep the values of a new
+/// AUR package.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SearchResult {
+    #[serde(rename = "ID")]
+    id: i64,
+    name: String,
+    #[serde(rename = "PackageBaseID")]
+    package_base_id: i64,
+    package_base: String,
+    version: String,
+    description: String,
+    #[serde(rename = "URL")]
+    url: String,
+    num_votes: i64,
+    popularity: f64,
+    out_of_date: Option<i64>,
+    maintainer: Option<String>,
+    first_submitted: i64,
+    last_modified: i64,
+    #[serde(rename = "URLPath")]
+    url_path: String,
+}
+
+impl From<&(Package, Repository, User)> for SearchResult {
+    fn from((package, repository, user): &(Package, Repository, User)) -> Self {
+        SearchResult {
+            id: 0,
+            name: package.name.clone(),
+            package_base_id: 0,
+            package_base: package.name.clone(),
+            version: package.version.clone(),
+            description: package.description.clone(),
+            url: package.url.clone(),
+            num_votes: 0,
+            popularity: 0.0,
+            out_of_date: None,
+            maintainer: Some(user.name.clone()),
+            first_submitted: package.creation_date.timestamp(),
+            last_modified: package.modification_date.timestamp(),
+            url_path: format!(
+                "/package/{}/{}/{}.{}",
+                repository.name, package.architecture, package.name, package.extension
+            ),
+        }
+    }
+}
+
+/// Keeps the highest version of each package name, as AUR names are unique while the same package
+/// may be in several repositories and architectures.
+fn deduplicate(packages: Vec<(Package, Repository, User)>) -> Vec<(Package, Repository, User)> {
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+    let mut latest_packages: Vec<(Package, Repository, User)> = Vec::new();
+    for package in packages {
+        match indexes.get(&package.0.name) {
+            Some(&index) => {
+                if alpm::vercmp(
+                    package.0.version.as_str(),
+                    &latest_packages[index].0.version,
+                ) == Ordering::Greater
+                {
+                    latest_packages[index] = package;
+                }
+            }
+            None => {
+                indexes.insert(package.0.name.clone(), latest_packages.len());
+                latest_packages.push(package);
+            }
+        }
+    }
+    latest_packages
+}
+
+fn error(message: &'static str) -> WarehouseResult<HttpResponse> {
+    Ok(HttpResponse::Ok().json(RpcResponse::<SearchResult> {
+        version: RPC_VERSION,
+        kind: "error",
+        resultcount: 0,
+        results: Vec::new(),
+        error: Some(message),
+    }))
+}
+
+fn info(connection: &PooledConnection, names: &[String]) -> WarehouseResult<HttpResponse> {
+    let results = deduplicate(Package::list_by_names(connection, names)?)
+        .iter()
+        .map(|package| {
+            Ok(InfoResult::new(
+                package,
+                &package.0.list_dependencies(connection)?,
+            ))
+        })
+        .collect::<WarehouseResult<Vec<InfoResult>>>()?;
+    Ok(HttpResponse::Ok().json(RpcResponse {
+        version: RPC_VERSION,
+        kind: "multiinfo",
+        resultcount: results.len(),
+        results,
+        error: None,
+    }))
+}
+
+fn search(connection: &PooledConnection, by: &str, arg: &str) -> WarehouseResult<HttpResponse> {
+    if by != "maintainer" && arg.chars().count() < 2 {
+        return error("Query arg too small.");
+    }
+    let limit = MAX_RESULTS as i64 + 1;
+    let packages = match by {
+        "name" => Package::list_by_name_search(connection, arg, false, limit)?,
+        "name-desc" => Package::list_by_name_search(connection, arg, true, limit)?,
+        "maintainer" => Package::list_by_maintainer(connection, arg, limit)?,
+        "depends" => Package::list_by_dependency(connection, "depend", arg, limit)?,
+        "makedepends" => Package::list_by_dependency(connection, "makedepend", arg, limit)?,
+        "optdepends" => Package::list_by_dependency(connection, "optdepend", arg, limit)?,
+        "checkdepends" => Package::list_by_dependency(connection, "checkdepend", arg, limit)?,
+        _ => return error("Incorrect by field specified."),
+    };
+    if packages.len() > MAX_RESULTS {
+        return error("Too many package results.");
+    }
+    let packages = deduplicate(packages);
+    Ok(HttpResponse::Ok().json(RpcResponse {
+        version: RPC_VERSION,
+        kind: "search",
+        resultcount: packages.len(),
+        results: packages
+            .iter()
+            .map(SearchResult::from)
+            .collect::<Vec<SearchResult>>(),
+        error: None,
+    }))
+}
+
+/// Answers AUR RPC v5 `search` and `info` requests, so that AUR helpers can query packages.
+pub async fn serve_rpc(
+    connection: PooledConnection,
+    request: HttpRequest,
+) -> WarehouseResult<HttpResponse> {
+    let query = RpcQuery::parse(request.query_string());
+    match query.version.as_deref() {
+        Some("5") => {}
+        Some(_) => return error("Invalid version specified."),
+        None => return error("Please specify an API version."),
+    }
+    match (query.kind.as_deref(), query.args.first()) {
+        (Some("search"), Some(arg)) => {
+            search(&connection, query.by.as_deref().unwrap_or("name-desc"), arg)
+        }
+        (Some("info"), Some(_)) | (Some("multiinfo"), Some(_)) => info(&connection, &query.args),
+        (Some(_), Some(_)) => error("Incorrect request type specified."),
+        _ => error("No request type/data specified."),
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    core::error::{CreateSnapshotError, WarehouseError, WarehouseResult},
+    database::PooledConnection,
+    service::repository::model::Repository,
+    utils::{path, snapshot, storage::Storage},
+};
+use actix_web::{
+    error::BlockingError,
+    http::header::LOCATION,
+    web::{self, Data, Form, Path},
+    HttpRequest, HttpResponse, Result,
+};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use std::{io, sync::Arc};
+
+#[derive(Deserialize)]
+pub struct SnapshotForm {
+    repository: String,
+}
+
+#[derive(Deserialize)]
+pub struct SnapshotPath {
+    date: String,
+    repository: String,
+}
+
+#[derive(Deserialize)]
+pub struct SnapshotFilePath {
+    date: String,
+    repository: String,
+    architecture: String,
+    name: String,
+    extension: String,
+}
+
+pub async fn delete_snapshot(
+    connection: PooledConnection,
+    path: Path<SnapshotPath>,
+    storage: Data<Arc<dyn Storage>>,
+) -> WarehouseResult<HttpResponse> {
+    let repository = match (
+        NaiveDate::parse_from_str(&path.date, snapshot::DATE_FORMAT),
+        Repository::find_by_name(&connection, &path.repository)?,
+    ) {
+        (Ok(_), Some(repository)) => repository,
+        _ => return Ok(HttpResponse::NotFound().into()),
+    };
+    let is_deleted = web::block(move || -> io::Result<bool> {
+        if storage
+            .list(&path::snapshot_dir(&path.date, &repository.name))?
+            .is_empty()
+        {
+            return Ok(false);
+        }
+        snapshot::delete_snapshot(&storage, &path.date, &repository.name)?;
+        Ok(true)
+    })
+    .await?;
+    if is_deleted {
+        Ok(HttpResponse::Ok().into())
+    } else {
+        Ok(HttpResponse::NotFound().into())
+    }
+}
+
+pub async fn handle_create_snapshot_post(
+    connection: PooledConnection,
+    form: Form<SnapshotForm>,
+    storage: Data<Arc<dyn Storage>>,
+) -> WarehouseResult<HttpResponse> {
+    let repository = Repository::find_by_name(&connection, &form.repository)?
+        .ok_or(WarehouseError::InvalidFormData)?;
+    let date = Utc::now().format(snapshot::DATE_FORMAT).to_string();
+    match web::block(move || snapshot::create_snapshot(&storage, &repository, &date)).await {
+        Ok(()) | Err(BlockingError::Error(CreateSnapshotError::SnapshotExists(..))) => {
+            Ok(HttpResponse::Found().header(LOCATION, "/admin").finish())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+pub async fn serve_snapshot_archive(
+    path: Path<SnapshotFilePath>,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+) -> Result<HttpResponse> {
+    storage
+        .serve(
+            &path::snapshot_file(
+                &path.date,
+                &path.repository,
+                &path.architecture,
+                &path.name,
+                &path.extension,
+            ),
+            &request,
+        )
+        .await
+}
+
+pub async fn serve_snapshot_database(
+    path: Path<SnapshotFilePath>,
+    request: HttpRequest,
+    storage: Data<Arc<dyn Storage>>,
+) -> Result<HttpResponse> {
+    storage
+        .serve(
+            &path::snapshot_file(
+                &path.date,
+                &path.repository,
+                &path.architecture,
+                "repository",
+                &path.extension,
+            ),
+            &request,
+        )
+        .await
+}
@@ -1,14 +1,14 @@
 use crate::{
     core::error::{WarehouseError, WarehouseResult},
     database::PooledConnection,
-    service::user::model::User,
-    utils::regex::Regexes,
+    service::user::model::{Token, User},
+    utils::{auth, regex::Regexes},
     view,
 };
 use actix_identity::Identity;
 use actix_web::{
     http::header::LOCATION,
-    web::{Data, Form},
+    web::{Data, Form, Path},
     HttpRequest, HttpResponse,
 };
 use bcrypt::DEFAULT_COST;
@@ -30,6 +30,22 @@ pub struct SignUpForm {
     password_confirmation: String,
 }
 
+#[derive(Deserialize)]
+pub struct TokenForm {
+    name: String,
+}
+
+impl TokenForm {
+    pub fn is_valid(&self) -> bool {
+        !self.name.trim().is_empty() && self.name.chars().count() <= 255
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TokenPath {
+    id: String,
+}
+
 impl SignUpForm {
     pub fn is_valid(&self, regexes: &Regexes) -> bool {
         regexes.username.is_match(&self.username)
@@ -39,6 +55,48 @@ impl SignUpForm {
     }
 }
 
+pub async fn handle_create_token_post(
+    connection: PooledConnection,
+    form: Form<TokenForm>,
+    request: HttpRequest,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    if form.is_valid() {
+        let secret = auth::generate_token();
+        let token = Token {
+            id: Uuid::new_v4().to_string(),
+            creation_date: Utc::now().naive_utc(),
+            name: form.name.trim().to_string(),
+            hash: auth::hash_token(&secret),
+            user_id: user.id.clone(),
+        };
+        token.create(&connection)?;
+        view!(&request, "route/user/tokens", [
+            "user" => &user,
+            "tokens" => &Token::list_by_user(&connection, &user.id)?,
+            "secret" => &secret
+        ])
+    } else {
+        Err(WarehouseError::InvalidFormData)
+    }
+}
+
+pub async fn handle_delete_token_post(
+    connection: PooledConnection,
+    path: Path<TokenPath>,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    match Token::find_by_id_and_user(&connection, &path.id, &user.id)? {
+        Some(token) => {
+            token.delete(&connection)?;
+            Ok(HttpResponse::Found()
+                .header(LOCATION, "/user/tokens")
+                .finish())
+        }
+        None => Ok(HttpResponse::NotFound().into()),
+    }
+}
+
 pub async fn handle_sign_in_post(
     connection: PooledConnection,
     form: Form<SignInForm>,
@@ -97,3 +155,14 @@ pub async fn serve_sign_in_page(request: HttpRequest) -> WarehouseResult<HttpRes
 pub async fn serve_sign_up_page(request: HttpRequest) -> WarehouseResult<HttpResponse> {
     view!(&request, "route/user/sign_up")
 }
+
+pub async fn serve_tokens_page(
+    connection: PooledConnection,
+    request: HttpRequest,
+    user: User,
+) -> WarehouseResult<HttpResponse> {
+    view!(&request, "route/user/tokens", [
+        "user" => &user,
+        "tokens" => &Token::list_by_user(&connection, &user.id)?
+    ])
+}
@@ -1,4 +1,7 @@
-use crate::database::{schema::warehouse_user, Connection};
+use crate::database::{
+    schema::{warehouse_user, warehouse_user_token},
+    Connection,
+};
 use chrono::NaiveDateTime;
 use diesel::{
     dsl, result::Error, BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl,
@@ -50,6 +53,22 @@ impl User {
             .optional()?)
     }
 
+    pub fn find_by_name(connection: &Connection, name: &str) -> Result<Option<User>, Error> {
+        Ok(warehouse_user::table
+            .filter(warehouse_user::name.eq(name))
+            .first(connection)
+            .optional()?)
+    }
+
+    pub fn find_by_token_hash(connection: &Connection, hash: &str) -> Result<Option<User>, Error> {
+        Ok(warehouse_user::table
+            .inner_join(warehouse_user_token::table)
+            .select(warehouse_user::all_columns)
+            .filter(warehouse_user_token::hash.eq(hash))
+            .first(connection)
+            .optional()?)
+    }
+
     pub fn find_by_name_or_email(
         connection: &Connection,
         field: &str,
@@ -63,4 +82,58 @@ impl User {
             .first(connection)
             .optional()?)
     }
+
+    pub fn list(connection: &Connection) -> Result<Vec<User>, Error> {
+        Ok(warehouse_user::table
+            .order_by(warehouse_user::name)
+            .load(connection)?)
+    }
+}
+
+#[derive(Clone, Deserialize, Insertable, Queryable, Serialize)]
+#[table_name = "warehouse_user_token"]
+pub struct Token {
+    pub id: String,
+    pub creation_date: NaiveDateTime,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub hash: String,
+    pub user_id: String,
+}
+
+impl Token {
+    pub fn create(&self, connection: &Connection) -> Result<(), Error> {
+        dsl::insert_into(warehouse_user_token::table)
+            .values(self)
+            .execute(connection)?;
+        Ok(())
+    }
+
+    pub fn delete(&self, connection: &Connection) -> Result<(), Error> {
+        dsl::delete(warehouse_user_token::table.filter(warehouse_user_token::id.eq(&self.id)))
+            .execute(connection)?;
+        Ok(())
+    }
+
+    pub fn find_by_id_and_user(
+        connection: &Connection,
+        id: &str,
+        user_id: &str,
+    ) -> Result<Option<Token>, Error> {
+        Ok(warehouse_user_token::table
+            .filter(
+                warehouse_user_token::id
+                    .eq(id)
+                    .and(warehouse_user_token::user_id.eq(user_id)),
+            )
+            .first(connection)
+            .optional()?)
+    }
+
+    pub fn list_by_user(connection: &Connection, user_id: &str) -> Result<Vec<Token>, Error> {
+        Ok(warehouse_user_token::table
+            .filter(warehouse_user_token::user_id.eq(user_id))
+            .order_by(warehouse_user_token::creation_date)
+            .load(connection)?)
+    }
 }
@@ -0,0 +1,218 @@
+use crate::core::error::ReadPackageError;
+use bzip2::read::BzDecoder;
+use flate2::read::MultiGzDecoder;
+use liblzma::{read::XzDecoder, stream::Stream};
+use lz4_flex::frame::FrameDecoder;
+use std::{
+    fs::File,
+    io::{self, BufReader, Cursor, Read},
+    path::Path,
+};
+use tar::Archive;
+
+pub fn find_extension<P: AsRef<Path>>(path: P) -> Result<String, ReadPackageError> {
+    const LIMIT: usize = 262;
+    let file = File::open(&path)?;
+    let mut buffer = Vec::with_capacity(LIMIT);
+    file.take(LIMIT as u64).read_to_end(&mut buffer)?;
+    match buffer.as_slice() {
+        [0x1F, 0x9D, ..] => Ok(String::from("Z")),
+        [0x1F, 0x8B, ..] => Ok(String::from("gz")),
+        [0x42, 0x5A, 0x68, ..] => Ok(String::from("bz2")),
+        [0x28, 0xB5, 0x2F, 0xFD, ..] => Ok(String::from("zst")),
+        [0x4C, 0x5A, 0x49, 0x50, ..] => Ok(String::from("lz")),
+        [0x04, 0x22, 0x4D, 0x18, ..] => Ok(String::from("lz4")),
+        [0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00, 0x00, ..] => Ok(String::from("xz")),
+        buffer if buffer.len() == LIMIT && &buffer[257..] == b"ustar" => Ok(String::new()),
+        _ => Err(ReadPackageError::UnsupportedFileType),
+    }
+}
+
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Archive<Box<dyn Read>>, ReadPackageError> {
+    let extension = find_extension(&path)?;
+    let file = BufReader::new(File::open(&path)?);
+    let reader: Box<dyn Read> = match extension.as_str() {
+        "Z" => Box::new(Cursor::new(decompress_lzw(file)?)),
+        "gz" => Box::new(MultiGzDecoder::new(file)),
+        "bz2" => Box::new(BzDecoder::new(file)),
+        "zst" => Box::new(zstd::Decoder::with_buffer(file)?),
+        "lz" => Box::new(XzDecoder::new_stream(
+            file,
+            Stream::new_lzip_decoder(u64::MAX, 0).map_err(io::Error::from)?,
+        )),
+        "lz4" => Box::new(FrameDecoder::new(file)),
+        "xz" => Box::new(XzDecoder::new_multi_decoder(file)),
+        _ => Box::new(file),
+    };
+    Ok(Archive::new(reader))
+}
+
+/// Decompresses a stream produced by compress(1), which has no maintained crate.
+fn decompress_lzw<R: Read>(mut reader: R) -> io::Result<Vec<u8>> {
+    const CLEAR: usize = 256;
+    const INIT_BITS: usize = 9;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid LZW stream");
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input)?;
+    if input.len() < 3 {
+        return Err(invalid());
+    }
+    let max_bits = usize::from(input[2] & 0x1F);
+    let block_mode = input[2] & 0x80 != 0;
+    if !(INIT_BITS..=16).contains(&max_bits) {
+        return Err(invalid());
+    }
+    let input = &input[3..];
+    let total_bits = input.len() * 8;
+    let max_max_code = 1 << max_bits;
+    let mut prefixes = vec![0u16; max_max_code];
+    let mut suffixes: Vec<u8> = (0..max_max_code).map(|code| code as u8).collect();
+    let mut n_bits = INIT_BITS;
+    let mut max_code = (1 << n_bits) - 1;
+    let mut free_entry = if block_mode { CLEAR + 1 } else { CLEAR };
+    let mut position = 0;
+    let mut group_start = 0;
+    let mut old_code: Option<usize> = None;
+    let mut final_char = 0u8;
+    let mut stack = Vec::new();
+    let mut output = Vec::new();
+    // Codes are read in groups of n_bits bytes; a code size change discards the rest of the group.
+    let align = |position: usize, group_start: usize, n_bits: usize| {
+        let group_bits = n_bits * 8;
+        let used = (position - group_start) % group_bits;
+        if used == 0 {
+            position
+        } else {
+            position + group_bits - used
+        }
+    };
+    loop {
+        if free_entry > max_code {
+            position = align(position, group_start, n_bits);
+            group_start = position;
+            n_bits += 1;
+            max_code = if n_bits == max_bits {
+                max_max_code
+            } else {
+                (1 << n_bits) - 1
+            };
+        }
+        if position + n_bits > total_bits {
+            break;
+        }
+        let mut code = 0;
+        for bit in 0..n_bits {
+            let index = position + bit;
+            if input[index / 8] & (1 << (index % 8)) != 0 {
+                code |= 1 << bit;
+            }
+        }
+        position += n_bits;
+        let previous_code = match old_code {
+            Some(previous_code) => previous_code,
+            None => {
+                if code >= CLEAR {
+                    return Err(invalid());
+                }
+                final_char = code as u8;
+                old_code = Some(code);
+                output.push(final_char);
+                continue;
+            }
+        };
+        if code == CLEAR && block_mode {
+            prefixes.iter_mut().for_each(|prefix| *prefix = 0);
+            free_entry = CLEAR;
+            position = align(position, group_start, n_bits);
+            group_start = position;
+            n_bits = INIT_BITS;
+            max_code = (1 << n_bits) - 1;
+            continue;
+        }
+        let incoming_code = code;
+        if code >= free_entry {
+            if code > free_entry {
+                return Err(invalid());
+            }
+            stack.push(final_char);
+            code = previous_code;
+        }
+        while code >= CLEAR {
+            stack.push(suffixes[code]);
+            code = usize::from(prefixes[code]);
+        }
+        final_char = suffixes[code];
+        stack.push(final_char);
+        output.extend(stack.drain(..).rev());
+        if free_entry < max_max_code {
+            prefixes[free_entry] = previous_code as u16;
+            suffixes[free_entry] = final_char;
+            free_entry += 1;
+        }
+        old_code = Some(incoming_code);
+    }
+    // The last code is only padded to a byte, so any further bits left mean a truncated stream.
+    if total_bits.saturating_sub(position) >= 8 {
+        return Err(invalid());
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Streams produced by a compress(1) encoder, and checked against `gzip -d`.
+    const CLEAR: &[u8] = include_bytes!("../../tests/fixtures/lzw/clear.Z");
+    const CODE_WIDTH: &[u8] = include_bytes!("../../tests/fixtures/lzw/code_width.Z");
+    const KWKWK: &[u8] = include_bytes!("../../tests/fixtures/lzw/kwkwk.Z");
+    const TABLE_FULL: &[u8] = include_bytes!("../../tests/fixtures/lzw/table_full.Z");
+    const TEXT: &[u8] = include_bytes!("../../tests/fixtures/lzw/text.Z");
+
+    #[test]
+    fn decompress_lzw_follows_clear_code() {
+        assert_eq!(
+            decompress_lzw(CLEAR).ok(),
+            Some(b"TOBEORNOTTOBEORTOBEORNOT".repeat(3))
+        );
+    }
+
+    #[test]
+    fn decompress_lzw_grows_code_width() {
+        assert_eq!(
+            decompress_lzw(CODE_WIDTH).ok().as_deref(),
+            Some(&include_bytes!("../../tests/fixtures/lzw/code_width")[..])
+        );
+    }
+
+    #[test]
+    fn decompress_lzw_keeps_full_table() {
+        assert_eq!(
+            decompress_lzw(TABLE_FULL).ok().as_deref(),
+            Some(&include_bytes!("../../tests/fixtures/lzw/table_full")[..])
+        );
+    }
+
+    #[test]
+    fn decompress_lzw_reads_code_being_defined() {
+        assert_eq!(decompress_lzw(KWKWK).ok(), Some(vec![b'a'; 40]));
+    }
+
+    #[test]
+    fn decompress_lzw_reads_text() {
+        assert_eq!(
+            decompress_lzw(TEXT).ok().as_deref(),
+            Some(&b"TOBEORNOTTOBEORTOBEORNOT"[..])
+        );
+    }
+
+    #[test]
+    fn decompress_lzw_rejects_invalid_streams() {
+        assert!(decompress_lzw(&TEXT[..2]).is_err());
+        assert!(decompress_lzw(&[0x1F, 0x9D, 0x88][..]).is_err());
+        // Cuts the stream in the middle of its last code.
+        assert!(decompress_lzw(&CODE_WIDTH[..CODE_WIDTH.len() - 3]).is_err());
+        // Refers to code 300 while the next free entry is 257.
+        assert!(decompress_lzw(&[0x1F, 0x9D, 0x90, 0x61, 0x58, 0x02][..]).is_err());
+    }
+}
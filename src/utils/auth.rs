@@ -6,10 +6,12 @@ use actix_service::{Service, Transform};
 use actix_web::{
     dev::{Payload, ServiceRequest, ServiceResponse},
     error::ErrorUnauthorized,
-    http::header::LOCATION,
+    http::header::{AUTHORIZATION, LOCATION},
     Error, FromRequest, HttpMessage, HttpRequest, HttpResponse, Result,
 };
 use futures::future::{self, Either, Ready};
+use rand::Rng;
+use sha2::{Digest, Sha256};
 use std::{
     convert::TryFrom,
     task::{Context, Poll},
@@ -164,12 +166,25 @@ where
 
 impl<S> AuthenticationServiceMiddleware<S> {
     fn authenticate(request: &ServiceRequest) -> Result<(), WarehouseError> {
-        if let Some(id) = request.get_identity() {
-            if let Some(pool) = request.app_data::<Pool>() {
-                let connection = pool.get()?;
-                if let Some(user) = User::find_by_id(&connection, &id)? {
-                    request.extensions_mut().insert(AuthenticationItem { user });
-                }
+        let token = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|authorization| authorization.to_str().ok())
+            .and_then(|authorization| authorization.strip_prefix("Bearer "))
+            .map(str::trim);
+        let identity = request.get_identity();
+        if token.is_none() && identity.is_none() {
+            return Ok(());
+        }
+        if let Some(pool) = request.app_data::<Pool>() {
+            let connection = pool.get()?;
+            let user = match (token, identity) {
+                (Some(token), _) => User::find_by_token_hash(&connection, &hash_token(token))?,
+                (None, Some(id)) => User::find_by_id(&connection, &id)?,
+                (None, None) => None,
+            };
+            if let Some(user) = user {
+                request.extensions_mut().insert(AuthenticationItem { user });
             }
         }
         Ok(())
@@ -330,6 +345,14 @@ impl<S> RedirectIfAuthenticatedMiddleware<S> {
     }
 }
 
+pub fn generate_token() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
 impl FromRequest for User {
     type Error = Error;
     type Future = Ready<Result<User, Error>>;
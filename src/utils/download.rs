@@ -0,0 +1,108 @@
+use crate::{
+    core::error::{WarehouseError, WarehouseResult},
+    database::{Pool, PooledConnection},
+    service::package::model::{Download, Package},
+};
+use actix_web::web;
+use chrono::{NaiveDate, Utc};
+use diesel::Connection;
+use futures::{channel::mpsc, StreamExt};
+use log::error;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Eq, Hash, PartialEq)]
+struct DownloadKey {
+    repository: String,
+    architecture: String,
+    name: String,
+    extension: String,
+    day: NaiveDate,
+}
+
+/// Counts archive downloads without holding the requests. Counts are sent to a single task, which
+/// writes them in batches so that concurrent downloads never compete for the database.
+#[derive(Clone)]
+pub struct DownloadCounter {
+    sender: mpsc::UnboundedSender<DownloadKey>,
+}
+
+impl DownloadCounter {
+    pub fn start(pool: Pool) -> DownloadCounter {
+        let (sender, mut receiver) = mpsc::unbounded();
+        actix_rt::spawn(async move {
+            while let Some(key) = receiver.next().await {
+                let mut counts = HashMap::new();
+                *counts.entry(key).or_default() += 1;
+                while let Ok(Some(key)) = receiver.try_next() {
+                    *counts.entry(key).or_default() += 1;
+                }
+                let pool = pool.clone();
+                if let Err(error) = web::block(move || record_downloads(&pool, counts)).await {
+                    error!("Error while counting downloads: {}", error);
+                }
+            }
+        });
+        DownloadCounter { sender }
+    }
+
+    pub fn count(&self, repository: &str, architecture: &str, name: &str, extension: &str) {
+        let key = DownloadKey {
+            repository: repository.to_string(),
+            architecture: architecture.to_string(),
+            name: name.to_string(),
+            extension: extension.to_string(),
+            day: Utc::now().naive_utc().date(),
+        };
+        if self.sender.unbounded_send(key).is_err() {
+            error!("Download counter stopped");
+        }
+    }
+}
+
+/// Adds the counts to the current version of the downloaded packages. Architecture independent
+/// packages are looked up when none matches the architecture they were downloaded for.
+fn record_downloads(pool: &Pool, counts: HashMap<DownloadKey, i64>) -> WarehouseResult {
+    let connection = PooledConnection::get(pool)?;
+    connection.transaction::<_, WarehouseError, _>(|| {
+        for (key, count) in counts {
+            let mut package = Package::find_by_name_repository_and_architecture(
+                &connection,
+                &key.name,
+                &key.repository,
+                &key.architecture,
+            )?;
+            if package.is_none() {
+                package = Package::find_by_name_repository_and_architecture(
+                    &connection,
+                    &key.name,
+                    &key.repository,
+                    "any",
+                )?;
+            }
+            let package = match package {
+                Some((package, _, _)) if package.extension == key.extension => package,
+                _ => continue,
+            };
+            if !Download::increment(
+                &connection,
+                &package.id,
+                &package.version,
+                &key.architecture,
+                key.day,
+                count,
+            )? {
+                let download = Download {
+                    id: Uuid::new_v4().to_string(),
+                    day: key.day,
+                    version: package.version,
+                    architecture: key.architecture,
+                    count,
+                    package_id: package.id,
+                };
+                download.create(&connection)?;
+            }
+        }
+        Ok(())
+    })
+}
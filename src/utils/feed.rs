@@ -0,0 +1,193 @@
+use crate::service::{
+    package::model::{Package, Version},
+    repository::model::Repository,
+    user::model::User,
+};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use quick_xml::{
+    events::{BytesDecl, BytesText, Event},
+    Error, Writer,
+};
+
+const ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
+const DUBLIN_CORE_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+
+type FeedWriter = Writer<Vec<u8>>;
+
+/// Package versions to publish, newest first. URLs are absolute, as feed readers require.
+pub struct Feed<'a> {
+    pub title: String,
+    pub url: String,
+    pub link: String,
+    pub base_url: String,
+    pub versions: &'a [(Version, Package, Repository, User)],
+}
+
+impl<'a> Feed<'a> {
+    fn entry_link(&self, package: &Package, repository: &Repository) -> String {
+        format!(
+            "{}/package/{}/{}/{}",
+            self.base_url, repository.name, package.architecture, package.name
+        )
+    }
+
+    /// Tells the creation of a package from its later versions, the first version being stored
+    /// along with the package.
+    fn entry_title(version: &Version, package: &Package, repository: &Repository) -> String {
+        format!(
+            "{} {} ({}) {} {}",
+            package.name,
+            version.version,
+            package.architecture,
+            if version.creation_date == package.creation_date {
+                "added to"
+            } else {
+                "updated in"
+            },
+            repository.name
+        )
+    }
+
+    fn updated(&self) -> DateTime<Utc> {
+        self.versions
+            .first()
+            .map(|(version, _, _, _)| utc(&version.creation_date))
+            .unwrap_or_else(Utc::now)
+    }
+
+    pub fn write_atom(&self) -> Result<Vec<u8>, Error> {
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+        writer
+            .create_element("feed")
+            .with_attribute(("xmlns", ATOM_NAMESPACE))
+            .write_inner_content(|writer| -> Result<(), Error> {
+                write_text(writer, "id", &self.url)?;
+                write_text(writer, "title", &self.title)?;
+                write_text(writer, "updated", &atom_date(&self.updated()))?;
+                writer
+                    .create_element("link")
+                    .with_attributes([("rel", "self"), ("href", self.url.as_str())])
+                    .write_empty()?;
+                writer
+                    .create_element("link")
+                    .with_attributes([
+                        ("rel", "alternate"),
+                        ("type", "text/html"),
+                        ("href", self.link.as_str()),
+                    ])
+                    .write_empty()?;
+                for entry in self.versions {
+                    self.write_atom_entry(writer, entry)?;
+                }
+                Ok(())
+            })?;
+        Ok(writer.into_inner())
+    }
+
+    fn write_atom_entry(
+        &self,
+        writer: &mut FeedWriter,
+        (version, package, repository, user): &(Version, Package, Repository, User),
+    ) -> Result<(), Error> {
+        writer
+            .create_element("entry")
+            .write_inner_content(|writer| -> Result<(), Error> {
+                write_text(writer, "id", &format!("urn:uuid:{}", version.id))?;
+                write_text(
+                    writer,
+                    "title",
+                    &Feed::entry_title(version, package, repository),
+                )?;
+                write_text(writer, "updated", &atom_date(&utc(&version.creation_date)))?;
+                writer
+                    .create_element("author")
+                    .write_inner_content(|writer| write_text(writer, "name", &user.name))?;
+                writer
+                    .create_element("link")
+                    .with_attributes([
+                        ("rel", "alternate"),
+                        ("href", self.entry_link(package, repository).as_str()),
+                    ])
+                    .write_empty()?;
+                write_text(writer, "summary", &package.description)
+            })?;
+        Ok(())
+    }
+
+    pub fn write_rss(&self) -> Result<Vec<u8>, Error> {
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+        writer
+            .create_element("rss")
+            .with_attributes([
+                ("version", "2.0"),
+                ("xmlns:atom", ATOM_NAMESPACE),
+                ("xmlns:dc", DUBLIN_CORE_NAMESPACE),
+            ])
+            .write_inner_content(|writer| -> Result<(), Error> {
+                writer.create_element("channel").write_inner_content(
+                    |writer| -> Result<(), Error> {
+                        write_text(writer, "title", &self.title)?;
+                        write_text(writer, "link", &self.link)?;
+                        write_text(writer, "description", &self.title)?;
+                        writer
+                            .create_element("atom:link")
+                            .with_attributes([
+                                ("href", self.url.as_str()),
+                                ("rel", "self"),
+                                ("type", "application/rss+xml"),
+                            ])
+                            .write_empty()?;
+                        write_text(writer, "lastBuildDate", &self.updated().to_rfc2822())?;
+                        for item in self.versions {
+                            self.write_rss_item(writer, item)?;
+                        }
+                        Ok(())
+                    },
+                )?;
+                Ok(())
+            })?;
+        Ok(writer.into_inner())
+    }
+
+    fn write_rss_item(
+        &self,
+        writer: &mut FeedWriter,
+        (version, package, repository, user): &(Version, Package, Repository, User),
+    ) -> Result<(), Error> {
+        writer
+            .create_element("item")
+            .write_inner_content(|writer| -> Result<(), Error> {
+                write_text(
+                    writer,
+                    "title",
+                    &Feed::entry_title(version, package, repository),
+                )?;
+                write_text(writer, "link", &self.entry_link(package, repository))?;
+                writer
+                    .create_element("guid")
+                    .with_attribute(("isPermaLink", "false"))
+                    .write_text_content(BytesText::new(&format!("urn:uuid:{}", version.id)))?;
+                write_text(writer, "pubDate", &utc(&version.creation_date).to_rfc2822())?;
+                write_text(writer, "dc:creator", &user.name)?;
+                write_text(writer, "description", &package.description)
+            })?;
+        Ok(())
+    }
+}
+
+fn atom_date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn utc(date: &NaiveDateTime) -> DateTime<Utc> {
+    Utc.from_utc_datetime(date)
+}
+
+fn write_text(writer: &mut FeedWriter, name: &str, text: &str) -> Result<(), Error> {
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::new(text))?;
+    Ok(())
+}
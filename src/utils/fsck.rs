@@ -0,0 +1,404 @@
+use crate::{
+    core::{config::Config, error::CheckRepositoryError},
+    database::PooledConnection,
+    service::{package::model::Package, repository::model::Repository},
+    utils::{package, path, repository, storage::Storage},
+};
+use derive_more::Display;
+use diesel::Connection;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    sync::Arc,
+};
+
+#[derive(Display, Serialize)]
+#[display(fmt = "[{}] {}: {}", kind, repository, message)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub repository: String,
+    pub message: String,
+    pub repaired: bool,
+}
+
+#[derive(Clone, Copy, Display, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    #[display(fmt = "missing archive")]
+    MissingArchive,
+
+    #[display(fmt = "missing entry")]
+    MissingEntry,
+
+    #[display(fmt = "orphaned file")]
+    OrphanedFile,
+
+    #[display(fmt = "stale entry")]
+    StaleEntry,
+}
+
+/// Cross-checks the package table, the archives in storage and the repository databases. When
+/// `repair` is set, archives are relinked or their packages dropped, orphaned files are removed and
+/// outdated databases are rewritten, in that order.
+pub fn check(
+    connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    repair: bool,
+) -> Result<Vec<Issue>, CheckRepositoryError> {
+    let repositories = Repository::list(connection)?;
+    let mut issues = Vec::new();
+    for repository in &repositories {
+        connection.transaction::<_, CheckRepositoryError, _>(|| {
+            check_archives(connection, config, storage, repository, repair, &mut issues)?;
+            check_files(connection, storage, repository, repair, &mut issues)?;
+            check_databases(connection, config, storage, repository, repair, &mut issues)?;
+            Ok(())
+        })?;
+    }
+    let names: BTreeSet<String> = storage
+        .list(&path::packages_dir())?
+        .iter()
+        .filter_map(|key| key.iter().next())
+        .map(|name| name.to_string_lossy().to_string())
+        .collect();
+    for name in names {
+        if !repositories
+            .iter()
+            .any(|repository| repository.name == name)
+        {
+            if repair {
+                storage.delete_all(&path::repository_dir(&name))?;
+            }
+            issues.push(Issue {
+                kind: IssueKind::OrphanedFile,
+                repository: name,
+                message: String::from("Directory belongs to no repository"),
+                repaired: repair,
+            });
+        }
+    }
+    Ok(issues)
+}
+
+fn check_archives(
+    connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    repair: bool,
+    issues: &mut Vec<Issue>,
+) -> Result<(), CheckRepositoryError> {
+    for package in Package::list_by_repository(connection, &repository.id)? {
+        let package_path = path::package_file(
+            &repository.name,
+            &package.architecture,
+            &package.name,
+            &package.extension,
+        );
+        if !storage.exists(&package_path)? {
+            let version_path = path::version_file(
+                &repository.name,
+                &package.architecture,
+                &package.name,
+                &package.version,
+                &package.extension,
+            );
+            let message = format!(
+                "Archive {}/{}.{} of {} {} is missing",
+                package.architecture,
+                package.name,
+                package.extension,
+                package.name,
+                package.version
+            );
+            let relinkable = storage.exists(&version_path)?;
+            if repair && relinkable {
+                package::stage_package(connection, repository, package.clone(), None)?
+                    .apply(storage)?;
+            } else if repair {
+                package::delete_package(connection, config, storage, repository, &package)?;
+            }
+            issues.push(issue(
+                IssueKind::MissingArchive,
+                repository,
+                message,
+                repair,
+            ));
+            // Without any archive left, the whole package goes away along with its versions.
+            if !relinkable {
+                continue;
+            }
+        } else if package.architecture == "any" {
+            for architecture in repository.architectures.split_whitespace() {
+                if !storage.exists(&path::package_file(
+                    &repository.name,
+                    architecture,
+                    &package.name,
+                    &package.extension,
+                ))? {
+                    let message = format!(
+                        "Archive {}/{}.{} of {} {} is missing",
+                        architecture,
+                        package.name,
+                        package.extension,
+                        package.name,
+                        package.version
+                    );
+                    if repair {
+                        repository::link_package(
+                            storage,
+                            repository,
+                            &package.name,
+                            &package.extension,
+                        )?;
+                    }
+                    issues.push(issue(
+                        IssueKind::MissingArchive,
+                        repository,
+                        message,
+                        repair,
+                    ));
+                }
+            }
+        }
+        for (mut version, _) in package.list_versions(connection)? {
+            if version.extension.is_empty()
+                || storage.exists(&path::version_file(
+                    &repository.name,
+                    &package.architecture,
+                    &package.name,
+                    &version.version,
+                    &version.extension,
+                ))?
+            {
+                continue;
+            }
+            let message = format!(
+                "Archive {}/versions/{}-{}.{} of {} {} is missing",
+                package.architecture,
+                package.name,
+                version.version,
+                version.extension,
+                package.name,
+                version.version
+            );
+            if repair {
+                storage.delete(&path::version_file(
+                    &repository.name,
+                    &package.architecture,
+                    &package.name,
+                    &version.version,
+                    &format!("{}.sig", version.extension),
+                ))?;
+                version.extension = String::new();
+                version.update(connection)?;
+            }
+            issues.push(issue(
+                IssueKind::MissingArchive,
+                repository,
+                message,
+                repair,
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_databases(
+    connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    repair: bool,
+    issues: &mut Vec<Issue>,
+) -> Result<(), CheckRepositoryError> {
+    for architecture in repository.architectures.split_whitespace() {
+        let database_name = format!("{}/repository.{}", architecture, repository.extension);
+        let database_path =
+            path::repository_file(&repository.name, architecture, &repository.extension);
+        let packages =
+            Package::list_by_repository_and_architecture(connection, &repository.id, architecture)?;
+        let mut found = Vec::new();
+        let database = match storage.fetch(&database_path) {
+            Ok(file) => Some(repository::read_database(file.path())),
+            // Databases are only written with the first package, so a new repository has none.
+            Err(error) if error.kind() == io::ErrorKind::NotFound && packages.is_empty() => {
+                continue
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error.into()),
+        };
+        if database.is_none() {
+            found.push((
+                IssueKind::MissingEntry,
+                format!("Database {} is missing", database_name),
+            ));
+        } else if let Some(Ok(database)) = database {
+            let entries: BTreeMap<String, [String; 3]> = database
+                .into_iter()
+                .map(|mut fields| {
+                    let mut field = |key: &str| fields.remove(key).unwrap_or_default();
+                    (
+                        field("NAME"),
+                        [field("VERSION"), field("FILENAME"), field("SHA256SUM")],
+                    )
+                })
+                .collect();
+            let mut expected = BTreeMap::new();
+            for package in packages {
+                expected.insert(
+                    package.name.clone(),
+                    [
+                        package.version.clone(),
+                        format!("{}.{}", package.name, package.extension),
+                        package.sha256sum.clone(),
+                    ],
+                );
+            }
+            for (name, entry) in &entries {
+                match expected.get(name) {
+                    Some(fields) if fields == entry => {}
+                    Some(fields) => found.push((
+                        IssueKind::StaleEntry,
+                        format!(
+                            "Database {} lists {} {} instead of {}",
+                            database_name, name, entry[0], fields[0]
+                        ),
+                    )),
+                    None => found.push((
+                        IssueKind::StaleEntry,
+                        format!(
+                            "Database {} lists {} {} which is not published",
+                            database_name, name, entry[0]
+                        ),
+                    )),
+                }
+            }
+            for (name, fields) in expected
+                .iter()
+                .filter(|(name, _)| !entries.contains_key(*name))
+            {
+                found.push((
+                    IssueKind::MissingEntry,
+                    format!(
+                        "Database {} does not list {} {}",
+                        database_name, name, fields[0]
+                    ),
+                ));
+            }
+        } else {
+            found.push((
+                IssueKind::StaleEntry,
+                format!("Database {} cannot be read", database_name),
+            ));
+        }
+        if repair && !found.is_empty() {
+            repository::update_repository(connection, config, storage, repository, architecture)?;
+        }
+        for (kind, message) in found {
+            issues.push(issue(kind, repository, message, repair));
+        }
+    }
+    Ok(())
+}
+
+fn check_files(
+    connection: &PooledConnection,
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    repair: bool,
+    issues: &mut Vec<Issue>,
+) -> Result<(), CheckRepositoryError> {
+    let repository_dir = path::repository_dir(&repository.name);
+    let packages = Package::list_by_repository(connection, &repository.id)?;
+    let architectures: BTreeSet<&str> = repository
+        .architectures
+        .split_whitespace()
+        .chain(packages.iter().map(|package| package.architecture.as_str()))
+        .chain(Some("any"))
+        .collect();
+    let mut expected = BTreeSet::new();
+    for architecture in &architectures {
+        let has_database = repository
+            .architectures
+            .split_whitespace()
+            .any(|name| name == *architecture);
+        if has_database {
+            let files_extension = repository.extension.replacen("db", "files", 1);
+            for extension in &[
+                repository.extension.as_str(),
+                files_extension.as_str(),
+                "db",
+                "files",
+            ] {
+                expected.insert(format!("{}/repository.{}", architecture, extension));
+                expected.insert(format!("{}/repository.{}.sig", architecture, extension));
+            }
+        }
+        for package in packages.iter().filter(|package| {
+            package.architecture == *architecture || (package.architecture == "any" && has_database)
+        }) {
+            let filename = format!("{}/{}.{}", architecture, package.name, package.extension);
+            expected.insert(format!("{}.sig", filename));
+            expected.insert(filename);
+            if package.architecture != *architecture {
+                continue;
+            }
+            for (version, _) in package.list_versions(connection)? {
+                if !version.extension.is_empty() {
+                    let filename = format!(
+                        "{}/versions/{}-{}.{}",
+                        architecture, package.name, version.version, version.extension
+                    );
+                    expected.insert(format!("{}.sig", filename));
+                    expected.insert(filename);
+                }
+            }
+        }
+    }
+    // Files of unknown architectures are reported once per directory.
+    let mut orphaned_dirs = BTreeSet::new();
+    for key in storage.list(&repository_dir)? {
+        let name = key.to_string_lossy().to_string();
+        let mut components = key.iter().map(|component| component.to_string_lossy());
+        match (components.next(), components.next()) {
+            (Some(architecture), Some(_)) if !architectures.contains(architecture.as_ref()) => {
+                orphaned_dirs.insert(architecture.to_string());
+            }
+            _ if !expected.contains(&name) => {
+                if repair {
+                    storage.delete(&repository_dir.join(&key))?;
+                }
+                issues.push(orphan(repository, &name, repair));
+            }
+            _ => {}
+        }
+    }
+    for architecture in orphaned_dirs {
+        if repair {
+            storage.delete_all(&repository_dir.join(&architecture))?;
+        }
+        issues.push(orphan(repository, &architecture, repair));
+    }
+    Ok(())
+}
+
+fn issue(kind: IssueKind, repository: &Repository, message: String, repaired: bool) -> Issue {
+    Issue {
+        kind,
+        repository: repository.name.clone(),
+        message,
+        repaired,
+    }
+}
+
+fn orphan(repository: &Repository, name: &str, repaired: bool) -> Issue {
+    issue(
+        IssueKind::OrphanedFile,
+        repository,
+        format!("File {} belongs to no package", name),
+        repaired,
+    )
+}
@@ -1,5 +1,14 @@
+pub mod archive;
 pub mod auth;
+pub mod download;
+pub mod feed;
+pub mod fsck;
 pub mod package;
 pub mod path;
 pub mod regex;
+pub mod repository;
+pub mod resolver;
+pub mod signature;
+pub mod snapshot;
+pub mod storage;
 pub mod view;
@@ -1,26 +1,166 @@
 use crate::{
-    core::error::{ImportPackageError, ReadPackageError, WarehouseResult},
+    core::{
+        config::Config,
+        error::{
+            ImportPackageError, PromotePackageError, ReadPackageError, RollbackPackageError,
+            WriteRepositoryError,
+        },
+    },
     database::PooledConnection,
     service::{
         package::model::{Dependency, File, Package, Version},
         repository::model::Repository,
         user::model::User,
     },
-    utils::path,
+    utils::{
+        archive, path, repository,
+        resolver::{self, Resolver},
+        signature,
+        storage::Storage,
+    },
 };
-use alpm::{Alpm, SigLevel};
+use actix_web::HttpRequest;
 use awmp::Parts;
 use chrono::{NaiveDateTime, Utc};
 use diesel::Connection;
+use flate2::read::GzDecoder;
+use md5::Md5;
+use sha2::{Digest, Sha256};
 use std::{
+    cmp::Ordering,
+    collections::BTreeSet,
     fs,
-    io::Read,
+    io::{self, BufRead, BufReader, Read},
     path::Path,
-    process::{Command, Stdio},
+    sync::Arc,
 };
-use tempfile::NamedTempFile;
 use uuid::Uuid;
 
+pub const DEPENDENCY_KINDS: [(&str, &str); 7] = [
+    ("depend", "Dependencies"),
+    ("optdepend", "Optional dependencies"),
+    ("makedepend", "Build dependencies"),
+    ("checkdepend", "Check dependencies"),
+    ("provides", "Provides"),
+    ("conflict", "Conflicts"),
+    ("replaces", "Replaces"),
+];
+
+pub struct DependencyInfo {
+    pub kind: String,
+    pub name: String,
+    pub operator: String,
+    pub version: String,
+    pub description: String,
+}
+
+impl DependencyInfo {
+    pub fn parse(kind: &str, value: &str) -> DependencyInfo {
+        let (relation, description) = match value.split_once(": ") {
+            Some((relation, description)) if kind == "optdepend" => {
+                (relation.trim(), description.trim())
+            }
+            _ => (value, ""),
+        };
+        let is_operator = |character: char| matches!(character, '<' | '>' | '=');
+        let (name, constraint) =
+            relation.split_at(relation.find(is_operator).unwrap_or(relation.len()));
+        let version = constraint.trim_start_matches(is_operator);
+        DependencyInfo {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            operator: constraint[..constraint.len() - version.len()].to_string(),
+            version: version.to_string(),
+            description: description.to_string(),
+        }
+    }
+}
+
+pub struct ImportResult {
+    pub name: String,
+    pub result: Result<Vec<String>, ImportPackageError>,
+}
+
+/// Storage changes of a staged package, applied once the database changes are committed so that a
+/// rollback leaves the served archives as they were.
+pub struct StagedPackage {
+    pub repository: Repository,
+    pub package: Package,
+    pub previous_extension: Option<String>,
+    pub pruned_versions: Vec<Version>,
+}
+
+impl StagedPackage {
+    pub fn apply(&self, storage: &Arc<dyn Storage>) -> Result<(), WriteRepositoryError> {
+        let repository = &self.repository;
+        let package = &self.package;
+        if let Some(previous_extension) = &self.previous_extension {
+            if *previous_extension != package.extension {
+                for extension in &[
+                    previous_extension.clone(),
+                    format!("{}.sig", previous_extension),
+                ] {
+                    storage.delete(&path::package_file(
+                        &repository.name,
+                        &package.architecture,
+                        &package.name,
+                        extension,
+                    ))?;
+                }
+                if package.architecture == "any" {
+                    repository::unlink_package(
+                        storage,
+                        repository,
+                        &package.name,
+                        previous_extension,
+                    )?;
+                }
+            }
+        }
+        for extension in &[
+            package.extension.clone(),
+            format!("{}.sig", package.extension),
+        ] {
+            let version_path = path::version_file(
+                &repository.name,
+                &package.architecture,
+                &package.name,
+                &package.version,
+                extension,
+            );
+            let package_path = path::package_file(
+                &repository.name,
+                &package.architecture,
+                &package.name,
+                extension,
+            );
+            if storage.exists(&version_path)? {
+                storage.link(&version_path, &package_path)?;
+            } else {
+                storage.delete(&package_path)?;
+            }
+        }
+        if package.architecture == "any" {
+            repository::link_package(storage, repository, &package.name, &package.extension)?;
+        }
+        for version in &self.pruned_versions {
+            for extension in &[
+                version.extension.clone(),
+                format!("{}.sig", version.extension),
+            ] {
+                storage.delete(&path::version_file(
+                    &repository.name,
+                    &package.architecture,
+                    &package.name,
+                    &version.version,
+                    extension,
+                ))?;
+            }
+        }
+        Ok(())
+    }
+}
+
 pub struct PackageInfo {
     pub name: String,
     pub version: String,
@@ -28,64 +168,340 @@ pub struct PackageInfo {
     pub architecture: String,
     pub url: String,
     pub licenses: Vec<String>,
-    pub dependencies: Vec<String>,
+    pub dependencies: Vec<DependencyInfo>,
     pub compressed_size: i64,
     pub installed_size: i64,
     pub build_date: NaiveDateTime,
     pub files: Vec<(String, i64)>,
     pub extension: String,
+    pub sha256sum: String,
+    pub md5sum: String,
 }
 
 impl PackageInfo {
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<PackageInfo, ReadPackageError> {
-        let extension = find_extension(&path)?;
-        let alpm = Alpm::new("/", "/var/lib/pacman")?;
-        let path = path.as_ref().to_owned().display().to_string();
-        let package = alpm.pkg_load(&path, true, SigLevel::NONE)?;
+        let extension = archive::find_extension(&path)?;
+        let mut package_info = None;
+        let mut build_info = None;
+        let mut mtree = None;
+        let mut entries = Vec::new();
+        for entry in archive::open(&path)?.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            match name.as_str() {
+                ".PKGINFO" => package_info = Some(Fields::read(&mut entry)?),
+                ".BUILDINFO" => build_info = Some(Fields::read(&mut entry)?),
+                ".MTREE" => mtree = Some(read_mtree(GzDecoder::new(&mut entry))?),
+                _ if !name.starts_with('.') && !entry.header().entry_type().is_dir() => {
+                    entries.push((name, entry.header().size()? as i64))
+                }
+                _ => {}
+            }
+        }
+        let package_info = package_info
+            .ok_or_else(|| ReadPackageError::InvalidPackage(String::from("Missing .PKGINFO")))?;
+        let name = package_info.require("pkgname")?;
+        let version = package_info.require("pkgver")?;
+        let architecture = package_info.require("arch")?;
+        let build_date = package_info
+            .first("builddate")
+            .or_else(|| {
+                build_info
+                    .as_ref()
+                    .and_then(|fields| fields.first("builddate"))
+            })
+            .map(|value| parse_number(value, "builddate"))
+            .transpose()?
+            .unwrap_or_default();
+        if let Some(build_info) = &build_info {
+            for (build_key, package_key, value) in &[
+                ("pkgname", "pkgname", &name),
+                ("pkgver", "pkgver", &version),
+                ("pkgarch", "arch", &architecture),
+            ] {
+                if let Some(build_value) = build_info.first(build_key) {
+                    if build_value != value.as_str() {
+                        return Err(ReadPackageError::InvalidPackage(format!(
+                            ".BUILDINFO {} does not match .PKGINFO {}",
+                            build_key, package_key
+                        )));
+                    }
+                }
+            }
+        }
+        let (sha256sum, md5sum) = compute_checksums(&path)?;
         Ok(PackageInfo {
-            name: package.name().to_string(),
-            version: package.version().to_string(),
-            description: package.desc().to_string(),
-            architecture: package.arch().to_string(),
-            url: package.url().to_string(),
-            licenses: package.licenses().map(|elem| elem.to_string()).collect(),
-            dependencies: package.depends().map(|elem| elem.to_string()).collect(),
-            compressed_size: package.size(),
-            installed_size: package.isize(),
-            build_date: NaiveDateTime::from_timestamp(package.build_date(), 0),
-            files: package
-                .files()
-                .files()
+            description: package_info
+                .first("pkgdesc")
+                .unwrap_or_default()
+                .to_string(),
+            url: package_info.first("url").unwrap_or_default().to_string(),
+            licenses: package_info.all("license"),
+            dependencies: DEPENDENCY_KINDS
                 .iter()
-                .map(|elem| (elem.name().to_string(), elem.size()))
-                .filter(|(filename, _)| !filename.ends_with('/'))
+                .flat_map(|(kind, _)| {
+                    package_info
+                        .all(kind)
+                        .into_iter()
+                        .map(move |value| DependencyInfo::parse(kind, &value))
+                })
                 .collect(),
-            extension: format!("pkg.tar.{}", extension),
+            compressed_size: fs::metadata(&path)?.len() as i64,
+            installed_size: package_info
+                .first("size")
+                .map(|value| parse_number(value, "size"))
+                .transpose()?
+                .unwrap_or_default(),
+            build_date: NaiveDateTime::from_timestamp(build_date, 0),
+            files: mtree.unwrap_or(entries),
+            extension: if extension.is_empty() {
+                String::from("pkg.tar")
+            } else {
+                format!("pkg.tar.{}", extension)
+            },
+            name,
+            version,
+            architecture,
+            sha256sum,
+            md5sum,
         })
     }
 }
 
-pub fn add_package_to_repository(
-    package_path: &Path,
-    repository_path: &Path,
+struct Fields(Vec<(String, String)>);
+
+impl Fields {
+    fn all(&self, key: &str) -> Vec<String> {
+        self.0
+            .iter()
+            .filter(|(field, _)| field == key)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn first(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(field, _)| field == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn read<R: Read>(reader: R) -> io::Result<Fields> {
+        let mut fields = Vec::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if let Some((key, value)) = line.split_once(" = ") {
+                if !key.starts_with('#') {
+                    fields.push((key.trim().to_string(), value.trim().to_string()));
+                }
+            }
+        }
+        Ok(Fields(fields))
+    }
+
+    fn require(&self, key: &str) -> Result<String, ReadPackageError> {
+        self.first(key)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+            .ok_or_else(|| ReadPackageError::InvalidPackage(format!("Missing {} in .PKGINFO", key)))
+    }
+}
+
+fn check_architecture(
+    connection: &PooledConnection,
+    repository: &Repository,
+    name: &str,
+    architecture: &str,
 ) -> Result<(), ImportPackageError> {
-    Command::new("repo-add")
-        .arg("--remove")
-        .arg("--quiet")
-        .arg(repository_path)
-        .arg(package_path)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?;
+    let conflicting_architectures = if architecture == "any" {
+        repository.architectures.split_whitespace().collect()
+    } else if repository
+        .architectures
+        .split_whitespace()
+        .any(|supported_architecture| supported_architecture == architecture)
+    {
+        vec!["any"]
+    } else {
+        return Err(ImportPackageError::UnsupportedArchitecture(
+            architecture.to_string(),
+        ));
+    };
+    for conflicting_architecture in conflicting_architectures {
+        if Package::find_by_name_repository_and_architecture(
+            connection,
+            name,
+            &repository.name,
+            conflicting_architecture,
+        )?
+        .is_some()
+        {
+            return Err(ImportPackageError::ArchitectureConflict(
+                conflicting_architecture.to_string(),
+            ));
+        }
+    }
     Ok(())
 }
 
+fn compute_checksums<P: AsRef<Path>>(path: P) -> io::Result<(String, String)> {
+    let mut file = fs::File::open(path)?;
+    let mut sha256 = Sha256::new();
+    let mut md5 = Md5::new();
+    let mut buffer = [0; 8192];
+    loop {
+        let count = file.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        sha256.update(&buffer[..count]);
+        md5.update(&buffer[..count]);
+    }
+    Ok((hex::encode(sha256.finalize()), hex::encode(md5.finalize())))
+}
+
+/// Imports the packages of an existing pacman repository directory, as listed by its database.
+/// Signatures are read from `<file>.sig` or from the database itself. Packages whose dependencies
+/// are not met yet are retried once the others are in, and anything left out is reported.
+pub fn bootstrap_repository(
+    connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    directory: &Path,
+    repository: &Repository,
+    user: &User,
+) -> Result<Vec<ImportResult>, ImportPackageError> {
+    connection
+        .transaction::<_, ImportPackageError, _>(|| {
+            let mut filenames = Vec::new();
+            for entry in fs::read_dir(directory)? {
+                let path = entry?.path();
+                if let (true, Some(filename)) = (path.is_file(), path.file_name()) {
+                    filenames.push(filename.to_string_lossy().to_string());
+                }
+            }
+            filenames.sort();
+            let database = filenames
+                .iter()
+                .find(|filename| filename.contains(".db.tar") && !filename.ends_with(".sig"))
+                .ok_or_else(|| {
+                    ImportPackageError::DatabaseNotFound(directory.display().to_string())
+                })?;
+            let entries = repository::read_database(&directory.join(database))?;
+            let listed: Vec<String> = entries
+                .iter()
+                .filter_map(|fields| fields.get("FILENAME").cloned())
+                .collect();
+            let mut results: Vec<ImportResult> = filenames
+                .iter()
+                .filter(|filename| {
+                    filename.contains(".pkg.tar")
+                        && !filename.ends_with(".sig")
+                        && !listed.contains(filename)
+                })
+                .map(|filename| ImportResult {
+                    name: filename.clone(),
+                    result: Err(ImportPackageError::UnlistedArchive),
+                })
+                .collect();
+            let mut pending = Vec::new();
+            for mut fields in entries {
+                let filename = fields.remove("FILENAME").unwrap_or_default();
+                if !filenames.contains(&filename) {
+                    results.push(ImportResult {
+                        name: filename,
+                        result: Err(ImportPackageError::ArchiveNotFound),
+                    });
+                    continue;
+                }
+                let signature = match fs::read(directory.join(format!("{}.sig", filename))) {
+                    Ok(signature) => Some(signature),
+                    Err(_) => fields
+                        .get("PGPSIG")
+                        .and_then(|signature| base64::decode(signature).ok()),
+                };
+                pending.push((filename, signature));
+            }
+            let mut architectures = BTreeSet::new();
+            let resolver = Resolver::from_sync_dir(&config.resolver.sync_dir)?;
+            let mut staged_packages = Vec::new();
+            loop {
+                let count = pending.len();
+                let mut deferred = Vec::new();
+                for (filename, signature) in pending {
+                    let result = connection.transaction::<_, ImportPackageError, _>(|| {
+                        import_file(
+                            connection,
+                            config,
+                            storage,
+                            &resolver,
+                            repository,
+                            user,
+                            &directory.join(&filename),
+                            signature.clone(),
+                            None,
+                        )
+                    });
+                    match result {
+                        Ok((staged, unmet_dependencies)) => {
+                            architectures.extend(repository::find_architectures(
+                                repository,
+                                &staged.package.architecture,
+                            ));
+                            staged_packages.push(staged);
+                            results.push(ImportResult {
+                                name: filename,
+                                result: Ok(unmet_dependencies),
+                            });
+                        }
+                        Err(error @ ImportPackageError::UnmetDependencies(..)) => {
+                            deferred.push((filename, signature, error))
+                        }
+                        Err(error) => results.push(ImportResult {
+                            name: filename,
+                            result: Err(error),
+                        }),
+                    }
+                }
+                if deferred.is_empty() || deferred.len() == count {
+                    for (filename, _, error) in deferred {
+                        results.push(ImportResult {
+                            name: filename,
+                            result: Err(error),
+                        });
+                    }
+                    break;
+                }
+                pending = deferred
+                    .into_iter()
+                    .map(|(filename, signature, _)| (filename, signature))
+                    .collect();
+            }
+            for architecture in architectures {
+                repository::update_repository(
+                    connection,
+                    config,
+                    storage,
+                    repository,
+                    &architecture,
+                )?;
+            }
+            results.sort_by(|first, second| first.name.cmp(&second.name));
+            Ok((results, staged_packages))
+        })
+        .and_then(|(results, staged_packages)| {
+            for staged in staged_packages {
+                staged.apply(storage)?;
+            }
+            Ok(results)
+        })
+}
+
 pub fn create_or_update_package(
     connection: &PooledConnection,
     repository: &Repository,
     user: &User,
     info: &PackageInfo,
-) -> Result<(), ImportPackageError> {
+) -> Result<Package, ImportPackageError> {
     if let Some((mut package, _, _)) = Package::find_by_name_repository_and_architecture(
         connection,
         &info.name,
@@ -93,14 +509,13 @@ pub fn create_or_update_package(
         &info.architecture,
     )? {
         if user.admin || package.maintainer_id == user.id {
-            let old_version = alpm::Version::new(&package.version);
-            let new_version = alpm::Version::new(&info.version);
-            if new_version > old_version {
-                update_package(connection, user, info, &mut package)
+            if is_newer_version(&info.version, &package.version) {
+                update_package(connection, user, info, &mut package)?;
+                Ok(package)
             } else {
                 Err(ImportPackageError::OlderPackageVersion {
-                    old: old_version.to_string(),
-                    new: new_version.to_string(),
+                    old: package.version,
+                    new: info.version.clone(),
                 })
             }
         } else {
@@ -116,7 +531,7 @@ pub fn create_package(
     repository: &Repository,
     user: &User,
     info: &PackageInfo,
-) -> Result<(), ImportPackageError> {
+) -> Result<Package, ImportPackageError> {
     let now = Utc::now().naive_utc();
     let package = Package {
         id: Uuid::new_v4().to_string(),
@@ -134,13 +549,19 @@ pub fn create_package(
         extension: info.extension.clone(),
         repository_id: repository.id.clone(),
         maintainer_id: user.id.clone(),
+        sha256sum: info.sha256sum.clone(),
+        md5sum: info.md5sum.clone(),
     };
     package.create(connection)?;
     for dependency in &info.dependencies {
         let dependency = Dependency {
             id: Uuid::new_v4().to_string(),
-            name: dependency.clone(),
+            name: dependency.name.clone(),
             package_id: package.id.clone(),
+            kind: dependency.kind.clone(),
+            operator: dependency.operator.clone(),
+            version: dependency.version.clone(),
+            description: dependency.description.clone(),
         };
         dependency.create(connection)?;
     }
@@ -150,6 +571,7 @@ pub fn create_package(
             name: filename.clone(),
             size: *size,
             package_id: package.id.clone(),
+            basename: file_basename(filename),
         };
         file.create(connection)?;
     }
@@ -158,96 +580,620 @@ pub fn create_package(
         creation_date: now,
         version: info.version.clone(),
         maintainer_id: user.id.clone(),
-        package_id: package.id,
+        package_id: package.id.clone(),
+        extension: info.extension.clone(),
+        sha256sum: info.sha256sum.clone(),
+        md5sum: info.md5sum.clone(),
     };
     version.create(connection)?;
-    Ok(())
+    Ok(package)
 }
 
-pub fn find_extension<P: AsRef<Path>>(path: P) -> Result<String, ReadPackageError> {
-    const LIMIT: usize = 7;
-    let file = fs::File::open(&path)?;
-    let mut buffer = [0; LIMIT];
-    let mut handle = file.take(LIMIT as u64);
-    if handle.read(&mut buffer)? < LIMIT {
-        Err(ReadPackageError::UnsupportedFileType)
-    } else {
-        match buffer {
-            [0x1F, 0x9D, ..] => Ok(String::from("Z")),
-            [0x1F, 0x8B, ..] => Ok(String::from("gz")),
-            [0x42, 0x5A, 0x68, ..] => Ok(String::from("bz2")),
-            [0x28, 0xB5, 0x2F, 0xFD, ..] => Ok(String::from("zst")),
-            [0x4C, 0x5A, 0x49, 0x50, ..] => Ok(String::from("lz")),
-            [0x04, 0x22, 0x4D, 0x18, ..] => Ok(String::from("lz4")),
-            [0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00, 0x00] => Ok(String::from("xz")),
-            _ => Err(ReadPackageError::UnsupportedFileType),
+pub fn delete_package(
+    connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    package: &Package,
+) -> Result<(), WriteRepositoryError> {
+    connection.transaction::<_, WriteRepositoryError, _>(|| {
+        let versions = package.list_versions(connection)?;
+        package.delete_versions(connection)?;
+        package.delete_dependencies(connection)?;
+        package.delete_downloads(connection)?;
+        package.delete_files(connection)?;
+        package.delete(connection)?;
+        for architecture in repository::find_architectures(repository, &package.architecture) {
+            repository::update_repository(connection, config, storage, repository, &architecture)?;
+        }
+        if package.architecture == "any" {
+            repository::unlink_package(storage, repository, &package.name, &package.extension)?;
+        }
+        for extension in &[
+            package.extension.clone(),
+            format!("{}.sig", package.extension),
+        ] {
+            storage.delete(&path::package_file(
+                &repository.name,
+                &package.architecture,
+                &package.name,
+                extension,
+            ))?;
+        }
+        for (version, _) in versions
+            .iter()
+            .filter(|(version, _)| !version.extension.is_empty())
+        {
+            for extension in &[
+                version.extension.clone(),
+                format!("{}.sig", version.extension),
+            ] {
+                storage.delete(&path::version_file(
+                    &repository.name,
+                    &package.architecture,
+                    &package.name,
+                    &version.version,
+                    extension,
+                ))?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Reads the `X-Checksum-Sha256` header, holding one comma-separated hash per `file` part.
+pub fn expected_checksums(request: &HttpRequest) -> Vec<String> {
+    request
+        .headers()
+        .get("X-Checksum-Sha256")
+        .and_then(|checksums| checksums.to_str().ok())
+        .map(|checksums| {
+            checksums
+                .split(',')
+                .map(|checksum| checksum.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn file_basename(path: &str) -> String {
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn import_file(
+    connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    resolver: &Resolver,
+    repository: &Repository,
+    user: &User,
+    file: &Path,
+    signature: Option<Vec<u8>>,
+    checksum: Option<&str>,
+) -> Result<(StagedPackage, Vec<String>), ImportPackageError> {
+    let info = PackageInfo::from_file(file)?;
+    if let Some(checksum) = checksum {
+        if !checksum.eq_ignore_ascii_case(&info.sha256sum) {
+            return Err(ImportPackageError::ChecksumMismatch {
+                expected: checksum.to_string(),
+                actual: info.sha256sum,
+            });
         }
     }
+    check_architecture(connection, repository, &info.name, &info.architecture)?;
+    let signature = match signature {
+        Some(signature) => Some(signature::verify_signature(
+            &config.verification.keyring,
+            file,
+            &signature,
+        )?),
+        None if repository.require_signature => return Err(ImportPackageError::SignatureRequired),
+        None => None,
+    };
+    let unmet_dependencies =
+        resolver::find_unmet_dependencies(connection, resolver, repository, &info)?;
+    if repository.strict_dependencies && !unmet_dependencies.is_empty() {
+        return Err(ImportPackageError::UnmetDependencies(unmet_dependencies));
+    }
+    let previous_extension = Package::find_by_name_repository_and_architecture(
+        connection,
+        &info.name,
+        &repository.name,
+        &info.architecture,
+    )?
+    .map(|(package, _, _)| package.extension);
+    let package = create_or_update_package(connection, repository, user, &info)?;
+    let version_path = path::version_file(
+        &repository.name,
+        &info.architecture,
+        &info.name,
+        &info.version,
+        &info.extension,
+    );
+    persist_package(storage, file, &version_path)?;
+    let signature_path = path::version_file(
+        &repository.name,
+        &info.architecture,
+        &info.name,
+        &info.version,
+        &format!("{}.sig", info.extension),
+    );
+    persist_signature(storage, signature.as_deref(), &signature_path)?;
+    let staged = stage_package(connection, repository, package, previous_extension)?;
+    Ok((staged, unmet_dependencies))
 }
 
-pub fn import_package(
+/// Imports every `file` part in one transaction, each behind its own savepoint so that a bad file
+/// does not discard the others. Signatures are paired by file name (`<file>.sig`), or taken as is
+/// for a single file. When checksums are given, the n-th one must match the n-th file. The
+/// repository databases are rewritten once, after the last file, and the archives are linked once
+/// the transaction is committed.
+pub fn import_packages(
     connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
     parts: Parts,
+    checksums: &[String],
     user: &User,
+) -> Result<Vec<ImportResult>, ImportPackageError> {
+    connection
+        .transaction::<_, ImportPackageError, _>(|| {
+            let repository = parts
+                .texts
+                .as_pairs()
+                .iter()
+                .find(|(key, _)| *key == "repository")
+                .map(|(_, value)| *value)
+                .ok_or_else(|| ImportPackageError::TextFieldNotFound(String::from("repository")))?;
+            let repository = Repository::find_by_name(connection, repository)?
+                .ok_or_else(|| ImportPackageError::RepositoryNotFound(repository.to_string()))?;
+            let mut files = Vec::new();
+            let mut signatures = Vec::new();
+            for (key, file) in parts.files.into_inner() {
+                match key.as_str() {
+                    "file" => files.push(file),
+                    "signature" => signatures.push(file),
+                    _ => {}
+                }
+            }
+            if files.is_empty() {
+                return Err(ImportPackageError::FileNotFound);
+            }
+            let is_single = files.len() == 1 && signatures.len() == 1;
+            let mut signatures = signatures
+                .into_iter()
+                .map(|signature| {
+                    let signature = signature.map_err(ImportPackageError::MultipartError)?;
+                    let name = signature.original_file_name().map(String::from);
+                    Ok((name, fs::read(signature.into_inner().path())?))
+                })
+                .filter(|signature| !matches!(signature, Ok((_, content)) if content.is_empty()))
+                .collect::<Result<Vec<(Option<String>, Vec<u8>)>, ImportPackageError>>()?;
+            let mut results = Vec::new();
+            let mut architectures = BTreeSet::new();
+            let resolver = Resolver::from_sync_dir(&config.resolver.sync_dir)?;
+            let mut staged_packages = Vec::new();
+            for (index, file) in files.into_iter().enumerate() {
+                let checksum = if checksums.is_empty() {
+                    None
+                } else {
+                    Some(checksums.get(index).map_or("", String::as_str))
+                };
+                let name = match &file {
+                    Ok(file) => file.original_file_name().unwrap_or("file").to_string(),
+                    Err(_) => String::from("file"),
+                };
+                let signature_name = format!("{}.sig", name);
+                let signature = match signatures
+                    .iter()
+                    .position(|(name, _)| name.as_deref() == Some(signature_name.as_str()))
+                {
+                    Some(index) => Some(signatures.remove(index).1),
+                    None if is_single => signatures.pop().map(|(_, signature)| signature),
+                    None => None,
+                };
+                let result = connection.transaction::<_, ImportPackageError, _>(|| {
+                    let file = file
+                        .map_err(ImportPackageError::MultipartError)?
+                        .into_inner();
+                    import_file(
+                        connection,
+                        config,
+                        storage,
+                        &resolver,
+                        &repository,
+                        user,
+                        file.path(),
+                        signature,
+                        checksum,
+                    )
+                });
+                let result = result.map(|(staged, unmet_dependencies)| {
+                    architectures.extend(repository::find_architectures(
+                        &repository,
+                        &staged.package.architecture,
+                    ));
+                    staged_packages.push(staged);
+                    unmet_dependencies
+                });
+                results.push(ImportResult { name, result });
+            }
+            for architecture in architectures {
+                repository::update_repository(
+                    connection,
+                    config,
+                    storage,
+                    &repository,
+                    &architecture,
+                )?;
+            }
+            Ok((results, staged_packages))
+        })
+        .and_then(|(results, staged_packages)| {
+            for staged in staged_packages {
+                staged.apply(storage)?;
+            }
+            Ok(results)
+        })
+}
+
+/// Compares versions like pacman, epoch and pkgrel included.
+fn is_newer_version(version: &str, current: &str) -> bool {
+    alpm::vercmp(version, current) == Ordering::Greater
+}
+
+fn parse_number(value: &str, key: &str) -> Result<i64, ReadPackageError> {
+    value
+        .parse()
+        .map_err(|_| ReadPackageError::InvalidPackage(format!("Invalid {} in .PKGINFO", key)))
+}
+
+pub fn persist_package(
+    storage: &Arc<dyn Storage>,
+    file: &Path,
+    path: &Path,
 ) -> Result<(), ImportPackageError> {
-    connection.transaction::<_, ImportPackageError, _>(|| {
-        let repository = parts
-            .texts
-            .as_pairs()
-            .iter()
-            .find(|(key, _)| *key == "repository")
-            .map(|(_, value)| *value)
-            .ok_or_else(|| ImportPackageError::TextFieldNotFound(String::from("repository")))?;
-        let repository = Repository::find_by_name(connection, repository)?
-            .ok_or_else(|| ImportPackageError::RepositoryNotFound(repository.to_string()))?;
-        let file = parts
-            .files
-            .into_inner()
-            .into_iter()
-            .filter(|(key, _)| key == "file")
-            .map(|(_, result)| result)
-            .next()
-            .ok_or(ImportPackageError::FileNotFound)?
-            .map_err(ImportPackageError::MultipartError)?
-            .into_inner();
-        let info = PackageInfo::from_file(file.path())?;
-        create_or_update_package(&connection, &repository, user, &info)?;
-        let package_path = path::package_file(
+    storage.put(path, file)?;
+    Ok(())
+}
+
+pub fn persist_signature(
+    storage: &Arc<dyn Storage>,
+    signature: Option<&[u8]>,
+    path: &Path,
+) -> Result<(), ImportPackageError> {
+    match signature {
+        Some(signature) => storage.write(path, signature)?,
+        None => storage.delete(path)?,
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn promote_package(
+    connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    source: &Repository,
+    package: &Package,
+    target: &Repository,
+    keep: bool,
+    user: &User,
+) -> Result<Package, PromotePackageError> {
+    connection
+        .transaction::<_, PromotePackageError, _>(|| {
+            if source.id == target.id {
+                return Err(PromotePackageError::SameRepository);
+            }
+            check_architecture(connection, target, &package.name, &package.architecture)?;
+            let signature_path = path::package_signature_file(
+                &source.name,
+                &package.architecture,
+                &package.name,
+                &package.extension,
+            );
+            if target.require_signature && !storage.exists(&signature_path)? {
+                return Err(ImportPackageError::SignatureRequired.into());
+            }
+            let now = Utc::now().naive_utc();
+            let existing_package = Package::find_by_name_repository_and_architecture(
+                connection,
+                &package.name,
+                &target.name,
+                &package.architecture,
+            )?
+            .map(|(existing_package, _, _)| existing_package);
+            let previous_extension = existing_package
+                .as_ref()
+                .map(|existing_package| existing_package.extension.clone());
+            let promoted_package = match existing_package {
+                Some(existing_package) => {
+                    if !user.admin && existing_package.maintainer_id != user.id {
+                        return Err(ImportPackageError::UnauthorizedUpdate.into());
+                    }
+                    if !is_newer_version(&package.version, &existing_package.version) {
+                        return Err(ImportPackageError::OlderPackageVersion {
+                            old: existing_package.version,
+                            new: package.version.clone(),
+                        }
+                        .into());
+                    }
+                    let promoted_package = Package {
+                        id: existing_package.id,
+                        creation_date: existing_package.creation_date,
+                        modification_date: now,
+                        repository_id: target.id.clone(),
+                        ..package.clone()
+                    };
+                    promoted_package.update(connection)?;
+                    promoted_package.delete_dependencies(connection)?;
+                    promoted_package.delete_files(connection)?;
+                    promoted_package
+                }
+                None => {
+                    let promoted_package = Package {
+                        id: Uuid::new_v4().to_string(),
+                        creation_date: now,
+                        modification_date: now,
+                        repository_id: target.id.clone(),
+                        ..package.clone()
+                    };
+                    promoted_package.create(connection)?;
+                    promoted_package
+                }
+            };
+            for dependency in package.list_dependencies(connection)? {
+                let dependency = Dependency {
+                    id: Uuid::new_v4().to_string(),
+                    package_id: promoted_package.id.clone(),
+                    ..dependency
+                };
+                dependency.create(connection)?;
+            }
+            for file in package.list_files(connection)? {
+                let file = File {
+                    id: Uuid::new_v4().to_string(),
+                    package_id: promoted_package.id.clone(),
+                    ..file
+                };
+                file.create(connection)?;
+            }
+            let known_versions: Vec<String> = promoted_package
+                .list_versions(connection)?
+                .into_iter()
+                .map(|(version, _)| version.version)
+                .collect();
+            for (version, _) in package.list_versions(connection)? {
+                if known_versions.contains(&version.version) {
+                    continue;
+                }
+                if !version.extension.is_empty() {
+                    for extension in &[
+                        version.extension.clone(),
+                        format!("{}.sig", version.extension),
+                    ] {
+                        let source_path = path::version_file(
+                            &source.name,
+                            &package.architecture,
+                            &package.name,
+                            &version.version,
+                            extension,
+                        );
+                        let target_path = path::version_file(
+                            &target.name,
+                            &package.architecture,
+                            &package.name,
+                            &version.version,
+                            extension,
+                        );
+                        if storage.exists(&source_path)? {
+                            storage.copy(&source_path, &target_path)?;
+                            if !keep {
+                                storage.delete(&source_path)?;
+                            }
+                        }
+                    }
+                }
+                let version = Version {
+                    id: Uuid::new_v4().to_string(),
+                    package_id: promoted_package.id.clone(),
+                    ..version
+                };
+                version.create(connection)?;
+            }
+            let staged = publish_package(
+                connection,
+                config,
+                storage,
+                target,
+                promoted_package,
+                previous_extension,
+            )?;
+            if !keep {
+                delete_package(connection, config, storage, source, package)?;
+            }
+            Ok(staged)
+        })
+        .and_then(|staged| {
+            staged.apply(storage)?;
+            Ok(staged.package)
+        })
+}
+
+/// Clears the versions beyond the retention of the repository and returns them, their archives
+/// being left for the caller to delete.
+fn prune_versions(
+    connection: &PooledConnection,
+    repository: &Repository,
+    package: &Package,
+) -> Result<Vec<Version>, WriteRepositoryError> {
+    let mut pruned_versions = Vec::new();
+    let mut kept_paths = Vec::new();
+    for (mut version, _) in package.list_versions(connection)? {
+        if version.extension.is_empty() {
+            continue;
+        }
+        let version_path = path::version_file(
             &repository.name,
-            &info.architecture,
-            &info.name,
-            &info.extension,
+            &package.architecture,
+            &package.name,
+            &version.version,
+            &version.extension,
         );
-        persist_package(file, &package_path)?;
-        let repository_path =
-            path::repository_file(&repository.name, &info.architecture, &repository.extension);
-        add_package_to_repository(&package_path, &repository_path)?;
-        Ok(())
-    })
+        if kept_paths.contains(&version_path) {
+            continue;
+        }
+        if kept_paths.len() < repository.retention.max(1) as usize {
+            kept_paths.push(version_path);
+            continue;
+        }
+        pruned_versions.push(version.clone());
+        version.extension = String::new();
+        version.update(connection)?;
+    }
+    Ok(pruned_versions)
 }
 
-pub fn persist_package(file: NamedTempFile, path: &Path) -> Result<(), ImportPackageError> {
-    let (_, file_path) = file.keep()?;
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
+pub fn publish_package(
+    connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    package: Package,
+    previous_extension: Option<String>,
+) -> Result<StagedPackage, WriteRepositoryError> {
+    let staged = stage_package(connection, repository, package, previous_extension)?;
+    for architecture in repository::find_architectures(repository, &staged.package.architecture) {
+        repository::update_repository(connection, config, storage, repository, &architecture)?;
     }
-    fs::copy(file_path, path)?;
-    Ok(())
+    Ok(staged)
 }
 
-pub fn remove_package_from_repository(
-    package: &str,
-    repository_path: &std::path::Path,
-) -> WarehouseResult {
-    Command::new("repo-remove")
-        .arg(repository_path)
-        .arg(package)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?;
+fn read_mtree<R: Read>(reader: R) -> io::Result<Vec<(String, i64)>> {
+    let mut files = Vec::new();
+    let mut default_kind = String::from("file");
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("/set") => {
+                if let Some(kind) = tokens.find_map(|token| token.strip_prefix("type=")) {
+                    default_kind = kind.to_string();
+                }
+            }
+            Some(path) if path.starts_with("./") => {
+                let mut kind = default_kind.as_str();
+                let mut size = 0;
+                for token in tokens {
+                    match token.split_once('=') {
+                        Some(("type", value)) => kind = value,
+                        Some(("size", value)) => {
+                            size = value.parse().map_err(|_| {
+                                io::Error::new(io::ErrorKind::InvalidData, "Invalid .MTREE size")
+                            })?
+                        }
+                        _ => {}
+                    }
+                }
+                let path = unescape_mtree_path(&path[2..]);
+                if kind != "dir" && !path.starts_with('.') {
+                    files.push((path, size));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(files)
+}
+
+pub fn rollback_package(
+    connection: &PooledConnection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    package: &mut Package,
+    version_id: &str,
+    user: &User,
+) -> Result<(), RollbackPackageError> {
+    let staged = connection.transaction::<_, RollbackPackageError, _>(|| {
+        let version = Version::find_by_id_and_package(connection, version_id, &package.id)?
+            .filter(|version| !version.extension.is_empty())
+            .ok_or(RollbackPackageError::VersionNotFound)?;
+        let version_path = path::version_file(
+            &repository.name,
+            &package.architecture,
+            &package.name,
+            &version.version,
+            &version.extension,
+        );
+        let file = match storage.fetch(&version_path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Err(RollbackPackageError::VersionNotFound)
+            }
+            Err(error) => return Err(ReadPackageError::from(error).into()),
+        };
+        let info = PackageInfo::from_file(file.path())?;
+        let previous_extension = package.extension.clone();
+        update_package(connection, user, &info, package)?;
+        Ok(publish_package(
+            connection,
+            config,
+            storage,
+            repository,
+            package.clone(),
+            Some(previous_extension),
+        )?)
+    })?;
+    staged.apply(storage)?;
     Ok(())
 }
 
+/// Prunes the old versions of a package in the database. The returned changes link the archive
+/// of the current version and delete the pruned ones, leaving the repository databases untouched.
+pub fn stage_package(
+    connection: &PooledConnection,
+    repository: &Repository,
+    package: Package,
+    previous_extension: Option<String>,
+) -> Result<StagedPackage, WriteRepositoryError> {
+    let pruned_versions = prune_versions(connection, repository, &package)?;
+    Ok(StagedPackage {
+        repository: repository.clone(),
+        package,
+        previous_extension,
+        pruned_versions,
+    })
+}
+
+fn unescape_mtree_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut buffer = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escape = bytes.get(index + 1..index + 4).filter(|digits| {
+            bytes[index] == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit))
+        });
+        if let Some(digits) = escape {
+            buffer.push(
+                digits
+                    .iter()
+                    .fold(0u8, |value, digit| value.wrapping_mul(8) + (digit - b'0')),
+            );
+            index += 4;
+        } else {
+            buffer.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
 pub fn update_package(
     connection: &PooledConnection,
     user: &User,
@@ -263,6 +1209,8 @@ pub fn update_package(
     package.installed_size = info.installed_size;
     package.license = info.licenses.join(" ");
     package.extension = info.extension.clone();
+    package.sha256sum = info.sha256sum.clone();
+    package.md5sum = info.md5sum.clone();
     package.maintainer_id = user.id.clone();
     package.modification_date = now;
     package.update(connection)?;
@@ -270,8 +1218,12 @@ pub fn update_package(
     for dependency in &info.dependencies {
         let dependency = Dependency {
             id: Uuid::new_v4().to_string(),
-            name: dependency.clone(),
+            name: dependency.name.clone(),
             package_id: package.id.clone(),
+            kind: dependency.kind.clone(),
+            operator: dependency.operator.clone(),
+            version: dependency.version.clone(),
+            description: dependency.description.clone(),
         };
         dependency.create(connection)?;
     }
@@ -282,6 +1234,7 @@ pub fn update_package(
             name: filename.clone(),
             size: *size,
             package_id: package.id.clone(),
+            basename: file_basename(filename),
         };
         file.create(connection)?;
     }
@@ -291,7 +1244,153 @@ pub fn update_package(
         version: info.version.clone(),
         maintainer_id: user.id.clone(),
         package_id: package.id.clone(),
+        extension: info.extension.clone(),
+        sha256sum: info.sha256sum.clone(),
+        md5sum: info.md5sum.clone(),
     };
     version.create(connection)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn create_or_update_package_compares_versions() -> crate::core::error::WarehouseResult {
+        use crate::{
+            database::{self, PooledConnection},
+            service::package::model::tests::{create_package, create_repository, create_user},
+        };
+        let (_directory, pool) = database::open_test_pool()?;
+        let connection = PooledConnection::get(&pool)?;
+        let user = create_user(&connection)?;
+        let repository = create_repository(&connection, "core", "")?;
+        create_package(&connection, &repository, &user, "foo", "1.2-1")?;
+        let info = |version: &str| PackageInfo {
+            name: String::from("foo"),
+            version: version.to_string(),
+            description: String::new(),
+            architecture: String::from("x86_64"),
+            url: String::new(),
+            licenses: Vec::new(),
+            dependencies: Vec::new(),
+            compressed_size: 0,
+            installed_size: 0,
+            build_date: Utc::now().naive_utc(),
+            files: Vec::new(),
+            extension: String::from("pkg.tar.zst"),
+            sha256sum: String::new(),
+            md5sum: String::new(),
+        };
+        let package = create_or_update_package(&connection, &repository, &user, &info("1.10-1"))?;
+        assert_eq!(package.version, "1.10-1");
+        assert!(matches!(
+            create_or_update_package(&connection, &repository, &user, &info("1.9-1")),
+            Err(ImportPackageError::OlderPackageVersion { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn dependency_info_parses_relations() {
+        for (kind, value, expected) in &[
+            ("depend", "glibc", ("glibc", "", "", "")),
+            ("depend", "glibc>=2.33", ("glibc", ">=", "2.33", "")),
+            ("depend", "bash<=5.1.008-1", ("bash", "<=", "5.1.008-1", "")),
+            ("depend", "python<1:4.0", ("python", "<", "1:4.0", "")),
+            ("conflict", "foo>1.0", ("foo", ">", "1.0", "")),
+            ("provides", "libfoo.so=2-64", ("libfoo.so", "=", "2-64", "")),
+            ("optdepend", "python", ("python", "", "", "")),
+            (
+                "optdepend",
+                "python: for the scripts",
+                ("python", "", "", "for the scripts"),
+            ),
+            (
+                "optdepend",
+                "python>=3.9: for the scripts: and hooks",
+                ("python", ">=", "3.9", "for the scripts: and hooks"),
+            ),
+        ] {
+            let dependency = DependencyInfo::parse(kind, value);
+            assert_eq!(dependency.kind, *kind);
+            assert_eq!(
+                (
+                    dependency.name.as_str(),
+                    dependency.operator.as_str(),
+                    dependency.version.as_str(),
+                    dependency.description.as_str()
+                ),
+                *expected,
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn is_newer_version_follows_vercmp() {
+        for (version, current, expected) in &[
+            ("1.10-1", "1.2-1", true),
+            ("1.2-1", "1.10-1", false),
+            ("1.0-10", "1.0-9", true),
+            ("1:1.0-1", "2.0-1", true),
+            ("2.0-1", "1:1.0-1", false),
+            ("1.0-1", "1.0rc1-1", true),
+            ("1.0-1", "1.0-1", false),
+        ] {
+            assert_eq!(
+                is_newer_version(version, current),
+                *expected,
+                "{} > {}",
+                version,
+                current
+            );
+        }
+    }
+
+    #[test]
+    fn read_mtree_lists_files() -> io::Result<()> {
+        let mtree = "#mtree\n\
+            /set type=file uid=0 gid=0 mode=644\n\
+            ./.BUILDINFO time=1600000000.0 size=5000\n\
+            ./.PKGINFO time=1600000000.0 size=300\n\
+            ./usr time=1600000000.0 mode=755 type=dir\n\
+            ./usr/bin time=1600000000.0 mode=755 type=dir\n\
+            ./usr/bin/foo time=1600000000.0 mode=755 size=1234\n\
+            ./usr/lib/libfoo.so time=1600000000.0 type=link link=libfoo.so.1\n\
+            ./usr/share/doc/foo/read\\040me.txt time=1600000000.0 size=10\n\
+            /set type=dir mode=755\n\
+            ./usr/share/empty time=1600000000.0\n\
+            ./usr/share/notes time=1600000000.0 type=file size=7\n";
+        assert_eq!(
+            read_mtree(mtree.as_bytes())?,
+            vec![
+                (String::from("usr/bin/foo"), 1234),
+                (String::from("usr/lib/libfoo.so"), 0),
+                (String::from("usr/share/doc/foo/read me.txt"), 10),
+                (String::from("usr/share/notes"), 7),
+            ]
+        );
+        assert!(read_mtree(&b"./usr/bin/foo size=big\n"[..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn unescape_mtree_path_decodes_octal_escapes() {
+        for (path, expected) in &[
+            ("usr/bin/foo", "usr/bin/foo"),
+            ("read\\040me.txt", "read me.txt"),
+            ("caf\\303\\251", "café"),
+            ("back\\134slash", "back\\slash"),
+            ("tab\\011", "tab\t"),
+            ("short\\04", "short\\04"),
+            ("not\\8octal", "not\\8octal"),
+            ("trailing\\", "trailing\\"),
+        ] {
+            assert_eq!(unescape_mtree_path(path), *expected);
+        }
+    }
+}
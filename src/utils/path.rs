@@ -56,19 +56,41 @@ pub fn log_file() -> PathBuf {
 }
 
 pub fn package_file(repository: &str, architecture: &str, name: &str, extension: &str) -> PathBuf {
-    let mut path = data_dir();
-    path.push("packages");
-    path.push(repository);
+    let mut path = repository_dir(repository);
     path.push(architecture);
     path.push(name);
     path.set_extension(extension);
     path
 }
 
-pub fn repository_file(repository: &str, architecture: &str, extension: &str) -> PathBuf {
-    let mut path = data_dir();
+pub fn package_signature_file(
+    repository: &str,
+    architecture: &str,
+    name: &str,
+    extension: &str,
+) -> PathBuf {
+    package_file(
+        repository,
+        architecture,
+        name,
+        &format!("{}.sig", extension),
+    )
+}
+
+pub fn packages_dir() -> PathBuf {
+    let mut path = PathBuf::new();
     path.push("packages");
+    path
+}
+
+pub fn repository_dir(repository: &str) -> PathBuf {
+    let mut path = packages_dir();
     path.push(repository);
+    path
+}
+
+pub fn repository_file(repository: &str, architecture: &str, extension: &str) -> PathBuf {
+    let mut path = repository_dir(repository);
     path.push(architecture);
     path.push("repository");
     path.set_extension(extension);
@@ -89,6 +111,33 @@ pub fn resources_dir() -> PathBuf {
     path
 }
 
+pub fn snapshot_dir(date: &str, repository: &str) -> PathBuf {
+    let mut path = snapshots_dir();
+    path.push(date);
+    path.push(repository);
+    path
+}
+
+pub fn snapshot_file(
+    date: &str,
+    repository: &str,
+    architecture: &str,
+    name: &str,
+    extension: &str,
+) -> PathBuf {
+    let mut path = snapshot_dir(date, repository);
+    path.push(architecture);
+    path.push(name);
+    path.set_extension(extension);
+    path
+}
+
+pub fn snapshots_dir() -> PathBuf {
+    let mut path = PathBuf::new();
+    path.push("snapshots");
+    path
+}
+
 pub fn static_files_dir() -> PathBuf {
     let mut path = resources_dir();
     path.push("static");
@@ -100,3 +149,17 @@ pub fn templates_dir() -> PathBuf {
     path.push("templates");
     path
 }
+
+pub fn version_file(
+    repository: &str,
+    architecture: &str,
+    name: &str,
+    version: &str,
+    extension: &str,
+) -> PathBuf {
+    let mut path = repository_dir(repository);
+    path.push(architecture);
+    path.push("versions");
+    path.push(format!("{}-{}.{}", name, version, extension));
+    path
+}
@@ -22,7 +22,10 @@ macro_rules! regexes {
 }
 
 regexes! {
+    architectures: r"^[a-zA-Z0-9_]{1,20}( [a-zA-Z0-9_]{1,20})*$",
     email: r"^[a-zA-Z0-9.!#$%&’*+/=?^_`{|}~-]+@[a-zA-Z0-9-]+(?:\.[a-zA-Z0-9-]+)*$",
     password: r"^.{8,32}$",
+    repositories: r"^([a-zA-Z0-9_-]{1,20}( [a-zA-Z0-9_-]{1,20})*)?$",
+    repository: r"^[a-zA-Z0-9_-]{1,20}$",
     username: r"^[a-zA-Z0-9]{3,20}$",
 }
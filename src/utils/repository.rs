@@ -0,0 +1,437 @@
+use crate::{
+    core::{
+        config::Config,
+        error::{ReadPackageError, WriteRepositoryError},
+    },
+    database::Connection,
+    service::{
+        package::model::{Dependency, File, Package},
+        repository::model::Repository,
+    },
+    utils::{archive, path, signature, storage::Storage},
+};
+use bzip2::write::BzEncoder;
+use chrono::Utc;
+use flate2::write::GzEncoder;
+use liblzma::write::XzEncoder;
+use lz4_flex::frame::FrameEncoder;
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    path::Path,
+    sync::Arc,
+};
+use tar::{Builder, EntryType, Header};
+
+pub const EXTENSIONS: [&str; 6] = [
+    "db.tar",
+    "db.tar.bz2",
+    "db.tar.gz",
+    "db.tar.lz4",
+    "db.tar.xz",
+    "db.tar.zst",
+];
+
+const DEPENDS_FIELDS: [(&str, &str); 6] = [
+    ("depend", "DEPENDS"),
+    ("conflict", "CONFLICTS"),
+    ("provides", "PROVIDES"),
+    ("optdepend", "OPTDEPENDS"),
+    ("makedepend", "MAKEDEPENDS"),
+    ("checkdepend", "CHECKDEPENDS"),
+];
+
+pub fn find_architectures(repository: &Repository, architecture: &str) -> Vec<String> {
+    if architecture == "any" {
+        repository
+            .architectures
+            .split_whitespace()
+            .map(String::from)
+            .collect()
+    } else {
+        vec![architecture.to_string()]
+    }
+}
+
+/// Links the `any` packages of every repository into each of its architectures and rewrites their
+/// databases, for repositories that used to publish them apart.
+pub fn link_any_packages(
+    connection: &Connection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+) -> Result<(), WriteRepositoryError> {
+    for repository in Repository::list(connection)? {
+        for package in
+            Package::list_by_repository_and_architecture(connection, &repository.id, "any")?
+        {
+            link_package(storage, &repository, &package.name, &package.extension)?;
+        }
+        for architecture in repository.architectures.split_whitespace() {
+            update_repository(connection, config, storage, &repository, architecture)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn link_package(
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    name: &str,
+    extension: &str,
+) -> io::Result<()> {
+    for architecture in repository.architectures.split_whitespace() {
+        for extension in &[extension.to_string(), format!("{}.sig", extension)] {
+            let target = path::package_file(&repository.name, "any", name, extension);
+            let path = path::package_file(&repository.name, architecture, name, extension);
+            if storage.exists(&target)? {
+                storage.link(&target, &path)?;
+            } else {
+                storage.delete(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn unlink_package(
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    name: &str,
+    extension: &str,
+) -> io::Result<()> {
+    for architecture in repository.architectures.split_whitespace() {
+        for extension in &[extension.to_string(), format!("{}.sig", extension)] {
+            storage.delete(&path::package_file(
+                &repository.name,
+                architecture,
+                name,
+                extension,
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn update_repository(
+    connection: &Connection,
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    architecture: &str,
+) -> Result<(), WriteRepositoryError> {
+    let compression = find_compression(&repository.extension)?;
+    let signing_key = if config.signing.enabled {
+        Some(signature::load_secret_key(&config.signing.private_key)?)
+    } else {
+        None
+    };
+    let mtime = Utc::now().timestamp() as u64;
+    let mut database = Builder::new(Vec::new());
+    let mut files = Builder::new(Vec::new());
+    for package in
+        Package::list_by_repository_and_architecture(connection, &repository.id, architecture)?
+    {
+        let directory = format!("{}-{}", package.name, package.version);
+        let signature = read_signature(storage, repository, &package)?;
+        let dependencies = package.list_dependencies(connection)?;
+        let desc = format_desc(&package, &dependencies, signature.as_deref());
+        let depends = format_depends(&dependencies);
+        for builder in &mut [&mut database, &mut files] {
+            append_directory(builder, &directory, mtime)?;
+            append_file(builder, &format!("{}/desc", directory), &desc, mtime)?;
+            append_file(builder, &format!("{}/depends", directory), &depends, mtime)?;
+        }
+        append_file(
+            &mut files,
+            &format!("{}/files", directory),
+            &format_files(&package.list_files(connection)?),
+            mtime,
+        )?;
+    }
+    let files_extension = repository.extension.replacen("db", "files", 1);
+    for (extension, link_extension, builder) in [
+        (repository.extension.as_str(), "db", database),
+        (files_extension.as_str(), "files", files),
+    ] {
+        let archive_path = path::repository_file(&repository.name, architecture, extension);
+        let archive = compress(compression, &builder.into_inner()?)?;
+        storage.write(&archive_path, &archive)?;
+        storage.link(
+            &archive_path,
+            &path::repository_file(&repository.name, architecture, link_extension),
+        )?;
+        let signature_path = path::repository_file(
+            &repository.name,
+            architecture,
+            &format!("{}.sig", extension),
+        );
+        let signature_link_path = path::repository_file(
+            &repository.name,
+            architecture,
+            &format!("{}.sig", link_extension),
+        );
+        if let Some(signing_key) = &signing_key {
+            let signature =
+                signature::sign_data(signing_key, &config.signing.passphrase, &archive)?;
+            storage.write(&signature_path, &signature)?;
+            storage.link(&signature_path, &signature_link_path)?;
+        } else {
+            for path in &[signature_link_path, signature_path] {
+                storage.delete(path)?;
+            }
+        }
+    }
+    remove_stale_archives(storage, repository, architecture)?;
+    Ok(())
+}
+
+fn append_directory(builder: &mut Builder<Vec<u8>>, path: &str, mtime: u64) -> io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_path(path)?;
+    header.set_entry_type(EntryType::Directory);
+    header.set_mode(0o755);
+    header.set_mtime(mtime);
+    header.set_size(0);
+    header.set_cksum();
+    builder.append(&header, io::empty())
+}
+
+fn append_file(
+    builder: &mut Builder<Vec<u8>>,
+    path: &str,
+    content: &str,
+    mtime: u64,
+) -> io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_path(path)?;
+    header.set_entry_type(EntryType::Regular);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_size(content.len() as u64);
+    header.set_cksum();
+    builder.append(&header, content.as_bytes())
+}
+
+fn compress(compression: &str, data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    match compression {
+        "bz2" => {
+            let mut encoder = BzEncoder::new(&mut buffer, bzip2::Compression::best());
+            encoder.write_all(data)?;
+            encoder.finish()?;
+        }
+        "gz" => {
+            let mut encoder = GzEncoder::new(&mut buffer, flate2::Compression::best());
+            encoder.write_all(data)?;
+            encoder.finish()?;
+        }
+        "lz4" => {
+            let mut encoder = FrameEncoder::new(&mut buffer);
+            encoder.write_all(data)?;
+            encoder.finish().map_err(io::Error::from)?;
+        }
+        "xz" => {
+            let mut encoder = XzEncoder::new(&mut buffer, 6);
+            encoder.write_all(data)?;
+            encoder.finish()?;
+        }
+        "zst" => {
+            let mut encoder = zstd::Encoder::new(&mut buffer, 0)?;
+            encoder.write_all(data)?;
+            encoder.finish()?;
+        }
+        _ => buffer.extend_from_slice(data),
+    }
+    Ok(buffer)
+}
+
+fn find_compression(extension: &str) -> Result<&str, WriteRepositoryError> {
+    if EXTENSIONS.contains(&extension) {
+        Ok(extension
+            .trim_start_matches("db.tar")
+            .trim_start_matches('.'))
+    } else {
+        Err(WriteRepositoryError::UnsupportedExtension(
+            extension.to_string(),
+        ))
+    }
+}
+
+fn format_dependencies(dependencies: &[Dependency], kind: &str) -> Vec<String> {
+    dependencies
+        .iter()
+        .filter(|dependency| dependency.kind == kind)
+        .map(|dependency| {
+            let relation = format!(
+                "{}{}{}",
+                dependency.name, dependency.operator, dependency.version
+            );
+            if dependency.description.is_empty() {
+                relation
+            } else {
+                format!("{}: {}", relation, dependency.description)
+            }
+        })
+        .collect()
+}
+
+fn format_depends(dependencies: &[Dependency]) -> String {
+    let mut buffer = String::new();
+    for (kind, key) in &DEPENDS_FIELDS {
+        format_field(
+            &mut buffer,
+            key,
+            format_dependencies(dependencies, kind)
+                .iter()
+                .map(String::as_str),
+        );
+    }
+    buffer
+}
+
+fn format_desc(package: &Package, dependencies: &[Dependency], signature: Option<&str>) -> String {
+    let mut buffer = String::new();
+    format_field(
+        &mut buffer,
+        "FILENAME",
+        Some(format!("{}.{}", package.name, package.extension).as_str()),
+    );
+    format_field(&mut buffer, "NAME", Some(package.name.as_str()));
+    format_field(&mut buffer, "VERSION", Some(package.version.as_str()));
+    format_field(&mut buffer, "DESC", Some(package.description.as_str()));
+    format_field(
+        &mut buffer,
+        "CSIZE",
+        Some(package.compressed_size.to_string().as_str()),
+    );
+    format_field(
+        &mut buffer,
+        "ISIZE",
+        Some(package.installed_size.to_string().as_str()),
+    );
+    format_field(&mut buffer, "MD5SUM", Some(package.md5sum.as_str()));
+    format_field(&mut buffer, "SHA256SUM", Some(package.sha256sum.as_str()));
+    format_field(&mut buffer, "PGPSIG", signature);
+    format_field(&mut buffer, "URL", Some(package.url.as_str()));
+    format_field(&mut buffer, "LICENSE", package.license.split_whitespace());
+    format_field(&mut buffer, "ARCH", Some(package.architecture.as_str()));
+    format_field(
+        &mut buffer,
+        "BUILDDATE",
+        Some(package.build_date.timestamp().to_string().as_str()),
+    );
+    format_field(
+        &mut buffer,
+        "REPLACES",
+        format_dependencies(dependencies, "replaces")
+            .iter()
+            .map(String::as_str),
+    );
+    buffer
+}
+
+fn format_field<'a, I: IntoIterator<Item = &'a str>>(buffer: &mut String, key: &str, values: I) {
+    let values: Vec<&str> = values
+        .into_iter()
+        .filter(|value| !value.is_empty())
+        .collect();
+    if !values.is_empty() {
+        buffer.push_str(&format!("%{}%\n{}\n\n", key, values.join("\n")));
+    }
+}
+
+fn format_files(files: &[File]) -> String {
+    let mut buffer = String::new();
+    format_field(
+        &mut buffer,
+        "FILES",
+        files.iter().map(|file| file.name.as_str()),
+    );
+    buffer
+}
+
+/// Reads the `desc` fields of every package listed in a repository database, keeping the first
+/// value of each.
+pub fn read_database(path: &Path) -> Result<Vec<BTreeMap<String, String>>, ReadPackageError> {
+    let mut entries = Vec::new();
+    for entry in archive::open(path)?.entries()? {
+        let mut entry = entry?;
+        if !entry.path()?.ends_with("desc") {
+            continue;
+        }
+        let mut desc = String::new();
+        entry.read_to_string(&mut desc)?;
+        let mut fields = BTreeMap::new();
+        let mut lines = desc.lines();
+        while let Some(line) = lines.next() {
+            if line.len() > 2 && line.starts_with('%') && line.ends_with('%') {
+                fields.insert(
+                    line.trim_matches('%').to_string(),
+                    lines.next().unwrap_or_default().to_string(),
+                );
+            }
+        }
+        entries.push(fields);
+    }
+    Ok(entries)
+}
+
+/// Reads the signature kept along with the current version archive, which is linked only once the
+/// changes are committed. Archives imported before versions were kept have their signature served
+/// next to them.
+fn read_signature(
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    package: &Package,
+) -> io::Result<Option<String>> {
+    let version_path = path::version_file(
+        &repository.name,
+        &package.architecture,
+        &package.name,
+        &package.version,
+        &package.extension,
+    );
+    let signature_path = if storage.exists(&version_path)? {
+        path::version_file(
+            &repository.name,
+            &package.architecture,
+            &package.name,
+            &package.version,
+            &format!("{}.sig", package.extension),
+        )
+    } else {
+        path::package_signature_file(
+            &repository.name,
+            &package.architecture,
+            &package.name,
+            &package.extension,
+        )
+    };
+    match storage.read(&signature_path) {
+        Ok(signature) => Ok(Some(base64::encode(signature))),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+fn remove_stale_archives(
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    architecture: &str,
+) -> io::Result<()> {
+    for extension in EXTENSIONS
+        .iter()
+        .filter(|extension| **extension != repository.extension)
+    {
+        for extension in &[extension.to_string(), extension.replacen("db", "files", 1)] {
+            for extension in &[extension.clone(), format!("{}.sig", extension)] {
+                storage.delete(&path::repository_file(
+                    &repository.name,
+                    architecture,
+                    extension,
+                ))?;
+            }
+        }
+    }
+    Ok(())
+}
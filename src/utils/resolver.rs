@@ -0,0 +1,271 @@
+use crate::{
+    core::error::{ReadPackageError, ResolveDependenciesError},
+    database::Connection,
+    service::{package::model::Package, repository::model::Repository},
+    utils::{
+        archive,
+        package::{DependencyInfo, PackageInfo},
+    },
+};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs,
+    io::Read,
+    path::Path,
+};
+
+#[derive(Default)]
+pub struct Resolver {
+    providers: HashMap<String, HashSet<String>>,
+}
+
+impl Resolver {
+    pub fn add_provider(&mut self, name: &str, version: &str) {
+        self.providers
+            .entry(name.to_string())
+            .or_default()
+            .insert(version.to_string());
+    }
+
+    /// Loads every `.db` of a sync directory, such as `/var/lib/pacman/sync`. It is read once per
+    /// import, as these databases do not change while packages are imported.
+    pub fn from_sync_dir<P: AsRef<Path>>(
+        sync_dir: P,
+    ) -> Result<Resolver, ResolveDependenciesError> {
+        let mut resolver = Resolver::default();
+        if sync_dir.as_ref().is_dir() {
+            for entry in fs::read_dir(sync_dir)? {
+                let path = entry?.path();
+                if path.extension().and_then(|extension| extension.to_str()) == Some("db") {
+                    resolver.load_database(&path)?;
+                }
+            }
+        }
+        Ok(resolver)
+    }
+
+    pub fn is_satisfied(&self, dependency: &DependencyInfo) -> bool {
+        self.providers
+            .get(&dependency.name)
+            .into_iter()
+            .flatten()
+            .any(|version| satisfies(version, &dependency.operator, &dependency.version))
+    }
+
+    pub fn load_database<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ReadPackageError> {
+        let mut packages: HashMap<String, (String, String, Vec<String>)> = HashMap::new();
+        for entry in archive::open(path)?.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.to_string_lossy().into_owned();
+            let directory = match entry_path.rsplit_once('/') {
+                Some((directory, "desc")) | Some((directory, "depends")) => directory.to_string(),
+                _ => continue,
+            };
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            let (name, version, provides) = packages.entry(directory).or_default();
+            let mut key = "";
+            for line in content.lines() {
+                match line {
+                    "" => key = "",
+                    line if line.len() > 1 && line.starts_with('%') && line.ends_with('%') => {
+                        key = line
+                    }
+                    value => match key {
+                        "%NAME%" => *name = value.to_string(),
+                        "%VERSION%" => *version = value.to_string(),
+                        "%PROVIDES%" => provides.push(value.to_string()),
+                        _ => {}
+                    },
+                }
+            }
+        }
+        for (name, version, provides) in packages.values() {
+            self.add_provider(name, version);
+            for provide in provides {
+                let provide = DependencyInfo::parse("provides", provide);
+                self.add_provider(&provide.name, &provide.version);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lists the dependencies of a package that neither its repository, the repositories it depends
+/// on nor the sync databases can satisfy.
+pub fn find_unmet_dependencies(
+    connection: &Connection,
+    sync_resolver: &Resolver,
+    repository: &Repository,
+    info: &PackageInfo,
+) -> Result<Vec<String>, ResolveDependenciesError> {
+    let mut resolver = Resolver::default();
+    let mut repository_ids = vec![repository.id.clone()];
+    for name in repository.depends_on.split_whitespace() {
+        if let Some(dependency) = Repository::find_by_name(connection, name)? {
+            repository_ids.push(dependency.id);
+        }
+    }
+    for repository_id in &repository_ids {
+        for (package, provide) in
+            Package::list_with_provides_by_repository(connection, repository_id)?
+        {
+            let is_replaced = repository_id == &repository.id
+                && package.name == info.name
+                && package.architecture == info.architecture;
+            let is_compatible = info.architecture == "any"
+                || package.architecture == "any"
+                || package.architecture == info.architecture;
+            if is_replaced || !is_compatible {
+                continue;
+            }
+            resolver.add_provider(&package.name, &package.version);
+            if let Some(provide) = provide {
+                resolver.add_provider(&provide.name, &provide.version);
+            }
+        }
+    }
+    Ok(info
+        .dependencies
+        .iter()
+        .filter(|dependency| {
+            dependency.kind == "depend"
+                && !resolver.is_satisfied(dependency)
+                && !sync_resolver.is_satisfied(dependency)
+        })
+        .map(|dependency| {
+            format!(
+                "{}{}{}",
+                dependency.name, dependency.operator, dependency.version
+            )
+        })
+        .collect())
+}
+
+fn satisfies(version: &str, operator: &str, required: &str) -> bool {
+    if operator.is_empty() {
+        return true;
+    }
+    if version.is_empty() {
+        return false;
+    }
+    // Like pacman, a constraint without pkgrel ignores the pkgrel of the candidate.
+    let version = if required.contains('-') {
+        version
+    } else {
+        version
+            .rsplit_once('-')
+            .map_or(version, |(version, _)| version)
+    };
+    let ordering = alpm::vercmp(version, required);
+    match operator {
+        "<" => ordering == Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        "=" => ordering == Ordering::Equal,
+        ">=" => ordering != Ordering::Less,
+        ">" => ordering == Ordering::Greater,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn dependency(value: &str) -> DependencyInfo {
+        DependencyInfo::parse("depend", value)
+    }
+
+    #[test]
+    fn is_satisfied_by_provides() {
+        let mut resolver = Resolver::default();
+        resolver.add_provider("bash", "5.1.008-1");
+        resolver.add_provider("sh", "");
+        resolver.add_provider("libfoo.so", "2-64");
+        assert!(resolver.is_satisfied(&dependency("bash")));
+        assert!(resolver.is_satisfied(&dependency("bash>=5")));
+        assert!(resolver.is_satisfied(&dependency("sh")));
+        // Like pacman, an unversioned provision does not satisfy a versioned dependency.
+        assert!(!resolver.is_satisfied(&dependency("sh>=1")));
+        assert!(resolver.is_satisfied(&dependency("libfoo.so=2-64")));
+        assert!(!resolver.is_satisfied(&dependency("libfoo.so=3-64")));
+        assert!(!resolver.is_satisfied(&dependency("zsh")));
+    }
+
+    #[test]
+    fn load_database_reads_names_versions_and_provides() -> Result<(), ReadPackageError> {
+        let file = NamedTempFile::new()?;
+        let mut builder = tar::Builder::new(file.reopen()?);
+        for (path, content) in &[
+            (
+                "bash-5.1.008-1/desc",
+                "%FILENAME%\nbash-5.1.008-1-x86_64.pkg.tar.zst\n\n%NAME%\nbash\n\n\
+                 %VERSION%\n5.1.008-1\n\n%PROVIDES%\nsh\nlibreadline.so=8-64\n",
+            ),
+            ("bash-5.1.008-1/files", "%FILES%\nusr/bin/bash\n"),
+            ("glibc-2.33-4/desc", "%NAME%\nglibc\n\n%VERSION%\n2.33-4\n"),
+        ] {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, content.as_bytes())?;
+        }
+        builder.into_inner()?;
+        let mut resolver = Resolver::default();
+        resolver.load_database(file.path())?;
+        assert!(resolver.is_satisfied(&dependency("bash=5.1.008-1")));
+        assert!(resolver.is_satisfied(&dependency("glibc>=2.33")));
+        assert!(resolver.is_satisfied(&dependency("sh")));
+        assert!(resolver.is_satisfied(&dependency("libreadline.so>=8")));
+        assert!(!resolver.is_satisfied(&dependency("usr/bin/bash")));
+        Ok(())
+    }
+
+    #[test]
+    fn satisfies_compares_epochs() {
+        assert!(satisfies("1:1.0-1", ">", "2.0"));
+        assert!(satisfies("1:1.0-1", ">=", "1:1.0"));
+        assert!(!satisfies("2.0-1", ">=", "1:1.0"));
+        assert!(satisfies("0:2.0-1", "=", "2.0"));
+    }
+
+    #[test]
+    fn satisfies_ignores_pkgrel_when_not_required() {
+        assert!(satisfies("1.0-3", "=", "1.0"));
+        assert!(!satisfies("1.0-3", "=", "1.0-1"));
+        assert!(satisfies("1.0-3", ">", "1.0-1"));
+        assert!(!satisfies("1.0-3", ">", "1.0"));
+    }
+
+    #[test]
+    fn satisfies_handles_operators() {
+        for (version, operator, required, expected) in &[
+            ("1.2-1", "", "", true),
+            ("", "", "", true),
+            ("", ">=", "1.0", false),
+            ("1.2-1", "<", "1.3", true),
+            ("1.2-1", "<", "1.2", false),
+            ("1.2-1", "<=", "1.2", true),
+            ("1.2-1", "<=", "1.1", false),
+            ("1.2-1", "=", "1.2", true),
+            ("1.2-1", "=", "1.2.1", false),
+            ("1.2-1", ">=", "1.2", true),
+            ("1.2-1", ">=", "1.10", false),
+            ("1.10-1", ">", "1.9", true),
+            ("1.2rc1-1", ">", "1.2", false),
+            ("1.2-1", "!=", "1.2", false),
+        ] {
+            assert_eq!(
+                satisfies(version, operator, required),
+                *expected,
+                "{} {} {}",
+                version,
+                operator,
+                required
+            );
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::core::error::{SignFileError, VerifySignatureError};
+use pgp::{
+    composed::{ArmorOptions, Deserializable, DetachedSignature, SignedPublicKey, SignedSecretKey},
+    crypto::hash::HashAlgorithm,
+    packet::Signature,
+    ser::Serialize,
+    types::{KeyDetails, Password, VerifyingKey},
+};
+use std::{fs::File, io::Cursor, path::Path};
+
+pub fn export_public_key(key: &SignedSecretKey) -> Result<String, SignFileError> {
+    Ok(key
+        .to_public_key()
+        .to_armored_string(ArmorOptions::default())?)
+}
+
+pub fn load_secret_key<P: AsRef<Path>>(path: P) -> Result<SignedSecretKey, SignFileError> {
+    let (key, _) = SignedSecretKey::from_reader_single(File::open(path)?)?;
+    Ok(key)
+}
+
+pub fn sign_data(
+    key: &SignedSecretKey,
+    passphrase: &str,
+    data: &[u8],
+) -> Result<Vec<u8>, SignFileError> {
+    let signature = DetachedSignature::sign_binary_data(
+        rand::thread_rng(),
+        &key.primary_key,
+        &Password::from(passphrase),
+        HashAlgorithm::Sha256,
+        data,
+    )?;
+    Ok(signature.to_bytes()?)
+}
+
+/// Returns the signature in its binary form, which is the only one pacman understands.
+pub fn verify_signature<P: AsRef<Path>, Q: AsRef<Path>>(
+    keyring: P,
+    path: Q,
+    signature: &[u8],
+) -> Result<Vec<u8>, VerifySignatureError> {
+    let (signature, _) = DetachedSignature::from_reader_single(Cursor::new(signature))?;
+    let binary = signature.to_bytes()?;
+    let signature = signature.signature;
+    let (keys, _) = SignedPublicKey::from_reader_many(File::open(keyring)?)?;
+    for key in keys {
+        let key = key?;
+        if is_issuer(&signature, &key.primary_key) {
+            verify_with_key(&signature, &key.primary_key, &path)?;
+            return Ok(binary);
+        }
+        for subkey in &key.public_subkeys {
+            if is_issuer(&signature, &subkey.key)
+                && subkey.verify_bindings(&key.primary_key).is_ok()
+            {
+                verify_with_key(&signature, &subkey.key, &path)?;
+                return Ok(binary);
+            }
+        }
+    }
+    Err(VerifySignatureError::UnknownKey)
+}
+
+fn is_issuer<K: KeyDetails>(signature: &Signature, key: &K) -> bool {
+    signature.issuer_fingerprint().contains(&&key.fingerprint())
+        || signature.issuer_key_id().contains(&&key.legacy_key_id())
+}
+
+fn verify_with_key<K: VerifyingKey, P: AsRef<Path>>(
+    signature: &Signature,
+    key: &K,
+    path: P,
+) -> Result<(), VerifySignatureError> {
+    signature.verify(key, File::open(path)?)?;
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use crate::{
+    core::error::CreateSnapshotError,
+    database::Connection,
+    service::repository::model::Repository,
+    utils::{path, repository, storage::Storage},
+};
+use chrono::{NaiveDate, Utc};
+use log::error;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::Path,
+    sync::Arc,
+};
+
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Serialize)]
+pub struct Snapshot {
+    pub date: String,
+    pub repository: String,
+    pub architectures: Vec<String>,
+}
+
+/// Freezes the databases of a repository under `snapshots/<date>`, with copies of the version
+/// archives they list, then drops the snapshots beyond its retention. Packages uploaded before
+/// versions were kept fall back to their current archive. Local storage makes the copies hard
+/// links, archives being never rewritten in place. The snapshot is assembled aside and moved in
+/// place once complete.
+pub fn create_snapshot(
+    storage: &Arc<dyn Storage>,
+    repository: &Repository,
+    date: &str,
+) -> Result<(), CreateSnapshotError> {
+    let snapshot_dir = path::snapshot_dir(date, &repository.name);
+    if !storage.list(&snapshot_dir)?.is_empty() {
+        return Err(CreateSnapshotError::SnapshotExists(format!(
+            "{}/{}",
+            date, repository.name
+        )));
+    }
+    let temporary_dir = path::snapshot_dir(date, &format!(".{}.tmp", repository.name));
+    storage.delete_all(&temporary_dir)?;
+    for architecture in repository.architectures.split_whitespace() {
+        let database_path =
+            path::repository_file(&repository.name, architecture, &repository.extension);
+        let database = match storage.fetch(&database_path) {
+            Ok(database) => database,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error.into()),
+        };
+        let source_dir = path::repository_dir(&repository.name).join(architecture);
+        let target_dir = temporary_dir.join(architecture);
+        for key in storage.list(&source_dir)? {
+            if key.parent() == Some(Path::new(""))
+                && key.to_string_lossy().starts_with("repository.")
+            {
+                storage.copy(&source_dir.join(&key), &target_dir.join(&key))?;
+            }
+        }
+        for fields in repository::read_database(database.path())? {
+            let field = |name: &str| fields.get(name).map(String::as_str).unwrap_or_default();
+            let extension = match field("FILENAME").strip_prefix(&format!("{}.", field("NAME"))) {
+                Some(extension) if !extension.is_empty() => extension.to_string(),
+                _ => continue,
+            };
+            let version_path = path::version_file(
+                &repository.name,
+                field("ARCH"),
+                field("NAME"),
+                field("VERSION"),
+                &extension,
+            );
+            let has_version = storage.exists(&version_path)?;
+            for extension in &[format!("{}.sig", extension), extension] {
+                let source = if has_version {
+                    path::version_file(
+                        &repository.name,
+                        field("ARCH"),
+                        field("NAME"),
+                        field("VERSION"),
+                        extension,
+                    )
+                } else {
+                    path::package_file(&repository.name, field("ARCH"), field("NAME"), extension)
+                };
+                if storage.exists(&source)? {
+                    storage.copy(
+                        &source,
+                        &target_dir.join(format!("{}.{}", field("NAME"), extension)),
+                    )?;
+                }
+            }
+        }
+    }
+    storage.rename_all(&temporary_dir, &snapshot_dir)?;
+    prune_snapshots(storage, repository)?;
+    Ok(())
+}
+
+pub fn delete_snapshot(storage: &Arc<dyn Storage>, date: &str, repository: &str) -> io::Result<()> {
+    storage.delete_all(&path::snapshot_dir(date, repository))
+}
+
+/// Lists the snapshots from the most recent, skipping the ones still being assembled.
+pub fn list_snapshots(storage: &Arc<dyn Storage>) -> io::Result<Vec<Snapshot>> {
+    let mut architectures = BTreeMap::new();
+    for key in storage.list(&path::snapshots_dir())? {
+        let mut components = key.iter().map(|component| component.to_string_lossy());
+        if let (Some(date), Some(repository), Some(architecture), Some(_)) = (
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+        ) {
+            if NaiveDate::parse_from_str(&date, DATE_FORMAT).is_ok() && !repository.starts_with('.')
+            {
+                architectures
+                    .entry((date.to_string(), repository.to_string()))
+                    .or_insert_with(BTreeSet::new)
+                    .insert(architecture.to_string());
+            }
+        }
+    }
+    let mut snapshots: Vec<Snapshot> = architectures
+        .into_iter()
+        .map(|((date, repository), architectures)| Snapshot {
+            date,
+            repository,
+            architectures: architectures.into_iter().collect(),
+        })
+        .collect();
+    snapshots.sort_by(|first, second| {
+        second
+            .date
+            .cmp(&first.date)
+            .then_with(|| first.repository.cmp(&second.repository))
+    });
+    Ok(snapshots)
+}
+
+fn prune_snapshots(storage: &Arc<dyn Storage>, repository: &Repository) -> io::Result<()> {
+    if repository.snapshot_retention <= 0 {
+        return Ok(());
+    }
+    for snapshot in list_snapshots(storage)?
+        .iter()
+        .filter(|snapshot| snapshot.repository == repository.name)
+        .skip(repository.snapshot_retention as usize)
+    {
+        delete_snapshot(storage, &snapshot.date, &snapshot.repository)?;
+    }
+    Ok(())
+}
+
+/// Snapshots every repository whose interval has elapsed since its latest snapshot. A failing
+/// repository is logged and does not hold back the others.
+pub fn take_scheduled_snapshots(
+    connection: &Connection,
+    storage: &Arc<dyn Storage>,
+) -> Result<(), CreateSnapshotError> {
+    let today = Utc::now().naive_utc().date();
+    let snapshots = list_snapshots(storage)?;
+    for repository in Repository::list(connection)? {
+        if repository.snapshot_interval <= 0 {
+            continue;
+        }
+        let latest = snapshots
+            .iter()
+            .filter(|snapshot| snapshot.repository == repository.name)
+            .filter_map(|snapshot| NaiveDate::parse_from_str(&snapshot.date, DATE_FORMAT).ok())
+            .max();
+        match latest {
+            Some(latest)
+                if (today - latest).num_days() < i64::from(repository.snapshot_interval) => {}
+            _ => {
+                if let Err(error) =
+                    create_snapshot(storage, &repository, &today.format(DATE_FORMAT).to_string())
+                {
+                    error!(
+                        "Error while taking a snapshot of {}: {}",
+                        repository.name, error
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{config::Config, error::WarehouseResult},
+        database::{self, PooledConnection},
+        service::package::model::tests::{create_package, create_repository, create_user},
+        utils::storage::local::LocalStorage,
+    };
+
+    #[test]
+    fn create_snapshot_falls_back_to_package_files() -> WarehouseResult {
+        let (directory, pool) = database::open_test_pool()?;
+        let connection = PooledConnection::get(&pool)?;
+        let storage: Arc<dyn Storage> = Arc::new(LocalStorage::new(directory.path().join("data")));
+        let user = create_user(&connection)?;
+        let repository = create_repository(&connection, "core", "")?;
+        for name in &["current", "legacy"] {
+            create_package(&connection, &repository, &user, name, "1.0-1")?;
+        }
+        for extension in &["pkg.tar.zst", "pkg.tar.zst.sig"] {
+            storage.write(
+                &path::version_file("core", "x86_64", "current", "1.0-1", extension),
+                b"current",
+            )?;
+            storage.write(
+                &path::package_file("core", "x86_64", "legacy", extension),
+                b"legacy",
+            )?;
+        }
+        repository::update_repository(
+            &connection,
+            &Config::default(),
+            &storage,
+            &repository,
+            "x86_64",
+        )?;
+        create_snapshot(&storage, &repository, "2026-01-01")?;
+        let snapshot_dir = path::snapshot_dir("2026-01-01", "core").join("x86_64");
+        for (name, content) in &[("current", b"current".as_ref()), ("legacy", b"legacy")] {
+            for extension in &["pkg.tar.zst", "pkg.tar.zst.sig"] {
+                assert_eq!(
+                    storage.read(&snapshot_dir.join(format!("{}.{}", name, extension)))?,
+                    *content
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use crate::utils::storage::Storage;
+use actix_files::NamedFile;
+use actix_web::{HttpRequest, HttpResponse};
+use futures::future::{self, LocalBoxFuture};
+use std::{
+    fs, io,
+    io::Write,
+    os::unix,
+    path::{Component, Path, PathBuf},
+};
+use tempfile::NamedTempFile;
+
+/// Keeps the objects as files under the data directory. Links are relative symbolic links, so the
+/// directory can be moved or served as is.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> LocalStorage {
+        LocalStorage { root }
+    }
+
+    fn create_parent(path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(())
+    }
+
+    fn list_into(directory: &Path, prefix: &Path, keys: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+            let key = prefix.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                LocalStorage::list_into(&entry.path(), &key, keys)?;
+            } else {
+                keys.push(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the directories left empty above a path, up to the root.
+    fn prune_parents(&self, path: &Path) {
+        let mut parent = path.parent();
+        while let Some(directory) = parent {
+            if directory == self.root || fs::remove_dir(directory).is_err() {
+                break;
+            }
+            parent = directory.parent();
+        }
+    }
+
+    fn remove_file(path: &Path) -> io::Result<()> {
+        if fs::symlink_metadata(path).is_ok() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Storage for LocalStorage {
+    fn copy(&self, source: &Path, target: &Path) -> io::Result<()> {
+        let source = fs::canonicalize(self.root.join(source))?;
+        let target = self.root.join(target);
+        LocalStorage::create_parent(&target)?;
+        LocalStorage::remove_file(&target)?;
+        // Archives are never rewritten in place, so a hard link is as good as a copy. A copy is
+        // still made when the data directory spans several file systems.
+        if fs::hard_link(&source, &target).is_err() {
+            fs::copy(&source, &target)?;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &Path) -> io::Result<()> {
+        LocalStorage::remove_file(&self.root.join(key))
+    }
+
+    fn delete_all(&self, prefix: &Path) -> io::Result<()> {
+        let path = self.root.join(prefix);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&path)?,
+            Ok(_) => fs::remove_file(&path)?,
+            Err(_) => return Ok(()),
+        }
+        self.prune_parents(&path);
+        Ok(())
+    }
+
+    fn exists(&self, key: &Path) -> io::Result<bool> {
+        Ok(self.root.join(key).exists())
+    }
+
+    fn fetch(&self, key: &Path) -> io::Result<NamedTempFile> {
+        let mut file = NamedTempFile::new()?;
+        io::copy(
+            &mut fs::File::open(self.root.join(key))?,
+            file.as_file_mut(),
+        )?;
+        Ok(file)
+    }
+
+    fn link(&self, target: &Path, key: &Path) -> io::Result<()> {
+        let path = self.root.join(key);
+        LocalStorage::create_parent(&path)?;
+        let directory = key.parent().unwrap_or_else(|| Path::new(""));
+        let common = directory
+            .components()
+            .zip(target.components())
+            .take_while(|(first, second)| first == second)
+            .count();
+        let relative_target: PathBuf = directory
+            .components()
+            .skip(common)
+            .map(|_| Component::ParentDir)
+            .chain(target.components().skip(common))
+            .collect();
+        let mut temporary_path = path.as_os_str().to_owned();
+        temporary_path.push(".tmp");
+        LocalStorage::remove_file(Path::new(&temporary_path))?;
+        unix::fs::symlink(relative_target, &temporary_path)?;
+        fs::rename(temporary_path, path)?;
+        Ok(())
+    }
+
+    fn list(&self, prefix: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut keys = Vec::new();
+        let directory = self.root.join(prefix);
+        if directory.is_dir() {
+            LocalStorage::list_into(&directory, Path::new(""), &mut keys)?;
+        }
+        Ok(keys)
+    }
+
+    fn put(&self, key: &Path, file: &Path) -> io::Result<()> {
+        let path = self.root.join(key);
+        LocalStorage::create_parent(&path)?;
+        let mut temporary_file = NamedTempFile::new_in(path.parent().unwrap_or(&self.root))?;
+        io::copy(&mut fs::File::open(file)?, temporary_file.as_file_mut())?;
+        temporary_file.as_file().sync_all()?;
+        temporary_file.persist(path)?;
+        Ok(())
+    }
+
+    fn read(&self, key: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(key))
+    }
+
+    fn rename_all(&self, source: &Path, target: &Path) -> io::Result<()> {
+        let source = self.root.join(source);
+        if fs::symlink_metadata(&source).is_ok() {
+            let target = self.root.join(target);
+            LocalStorage::create_parent(&target)?;
+            fs::rename(&source, target)?;
+            self.prune_parents(&source);
+        }
+        Ok(())
+    }
+
+    fn serve(
+        &self,
+        key: &Path,
+        request: &HttpRequest,
+    ) -> LocalBoxFuture<'static, actix_web::Result<HttpResponse>> {
+        Box::pin(future::ready(
+            NamedFile::open(self.root.join(key))
+                .map_err(Into::into)
+                .and_then(|file| file.into_response(request)),
+        ))
+    }
+
+    fn write(&self, key: &Path, data: &[u8]) -> io::Result<()> {
+        let path = self.root.join(key);
+        LocalStorage::create_parent(&path)?;
+        let mut file = NamedTempFile::new_in(path.parent().unwrap_or(&self.root))?;
+        file.write_all(data)?;
+        file.as_file().sync_all()?;
+        file.persist(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{
+    core::{config::StorageConfig, error::OpenStorageError},
+    utils::path,
+};
+use actix_web::{HttpRequest, HttpResponse};
+use futures::future::LocalBoxFuture;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tempfile::NamedTempFile;
+
+pub mod local;
+pub mod s3;
+
+/// Holds the archives, repository databases and snapshots. Keys are the relative paths built by
+/// `utils::path`, such as `packages/<repository>/<architecture>/<file>`.
+pub trait Storage: Send + Sync {
+    /// Copies an object, resolving links, so that the copy outlives its source.
+    fn copy(&self, source: &Path, target: &Path) -> io::Result<()>;
+
+    /// Deletes an object, if any.
+    fn delete(&self, key: &Path) -> io::Result<()>;
+
+    /// Deletes every object below a prefix.
+    fn delete_all(&self, prefix: &Path) -> io::Result<()>;
+
+    fn exists(&self, key: &Path) -> io::Result<bool>;
+
+    /// Downloads an object to a temporary file, for the readers that need one.
+    fn fetch(&self, key: &Path) -> io::Result<NamedTempFile>;
+
+    /// Makes `key` serve the content of `target`, by a link where the backend has them and by a
+    /// copy otherwise.
+    fn link(&self, target: &Path, key: &Path) -> io::Result<()>;
+
+    /// Lists the objects below a prefix, relative to it.
+    fn list(&self, prefix: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Uploads a local file.
+    fn put(&self, key: &Path, file: &Path) -> io::Result<()>;
+
+    fn read(&self, key: &Path) -> io::Result<Vec<u8>>;
+
+    /// Moves every object below a prefix under another one.
+    fn rename_all(&self, source: &Path, target: &Path) -> io::Result<()>;
+
+    /// Answers a download, either with the object itself or with a redirection to it. Backends
+    /// reached over the network query them on the blocking thread pool.
+    fn serve(
+        &self,
+        key: &Path,
+        request: &HttpRequest,
+    ) -> LocalBoxFuture<'static, actix_web::Result<HttpResponse>>;
+
+    /// Replaces an object with the given content.
+    fn write(&self, key: &Path, data: &[u8]) -> io::Result<()>;
+}
+
+pub fn open(config: &StorageConfig) -> Result<Arc<dyn Storage>, OpenStorageError> {
+    match config.backend.as_str() {
+        "local" => Ok(Arc::new(local::LocalStorage::new(path::data_dir()))),
+        "s3" => Ok(Arc::new(s3::S3Storage::new(config)?)),
+        backend => Err(OpenStorageError::UnknownBackend(backend.to_string())),
+    }
+}
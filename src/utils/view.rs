@@ -11,8 +11,11 @@ struct AppContext {
 
 #[derive(Serialize)]
 struct RegexContext {
+    architectures: String,
     email: String,
     password: String,
+    repositories: String,
+    repository: String,
     username: String,
 }
 
@@ -56,8 +59,11 @@ impl ContextBuilder {
         self.context.insert(
             "regexes",
             &RegexContext {
+                architectures: regexes.architectures.to_string(),
                 email: regexes.email.to_string(),
                 password: regexes.password.to_string(),
+                repositories: regexes.repositories.to_string(),
+                repository: regexes.repository.to_string(),
                 username: regexes.username.to_string(),
             },
         );